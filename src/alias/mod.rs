@@ -0,0 +1,209 @@
+use crate::*;
+use std::io::Write;
+
+/// Return `true` if `name` can name an alias: it must not hold characters
+/// that would split, quote or expand it when typed.
+fn is_alias_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(|c| " \t\n|&;()<>'\"\\`$/=".contains(c))
+}
+
+/// Write an alias as the command that defines it.
+fn format_alias(name: &str, value: &str) -> String {
+    format!("alias {}={}", name, quote(value))
+}
+
+/// Run a simple command starting with an alias, the raw text of its value
+/// taking the place of the name. When the value ends with a blank, the word
+/// after it is checked for an alias too, as in `alias sudo='sudo '`.
+///
+/// The text is parsed again, so a value may hold several commands, as in
+/// `alias l='ls | cat'`. While they run, the aliases expanded here are not
+/// expanded again: `alias ls='ls -F'` runs `ls`, and aliases naming each
+/// other stop at the first one seen twice.
+///
+/// # Returns
+/// - `None` when the command does not start with an alias, otherwise the
+///   exit status of the commands it expanded to.
+pub fn exec_alias(command: &Command, state: &mut ShellState) -> Option<i32> {
+    let usable = |word: &String| {
+        let in_use = state.aliases_in_use.contains(word);
+        state.aliases.get(word).filter(|_| !in_use)
+    };
+    let mut text = usable(&command.name)?.clone();
+    let mut names = vec![command.name.clone()];
+    let mut args = command.args.as_slice();
+    while text.ends_with([' ', '\t'])
+        && let Some((word, rest)) = args.split_first()
+        && !names.contains(word)
+        && let Some(value) = usable(word)
+    {
+        text.push_str(value);
+        names.push(word.clone());
+        args = rest;
+    }
+
+    let expanded = Command {
+        name: text,
+        args: args.to_vec(),
+        ..command.clone()
+    };
+    let list = match expanded.to_string().custom_split() {
+        Ok(list) => list,
+        Err(ParseError::Incomplete(err) | ParseError::Syntax(err)) => {
+            print_error(&format!("{}: {}", command.name, err));
+            return Some(2);
+        }
+    };
+    let depth = state.aliases_in_use.len();
+    state.aliases_in_use.extend(names);
+    let status = exec_list(&list, state);
+    state.aliases_in_use.truncate(depth);
+    Some(status)
+}
+
+/// Define aliases, or print them as commands that define them again.
+///
+/// # Parameters
+/// - `args`: `NAME=value` words to define, `NAME` to print one alias. With
+///   none, or with `-p` first, every alias is printed, sorted by name.
+/// - `state`: shell state holding the aliases.
+/// - `stdout`: stream the aliases are printed to.
+/// - `stderr`: stream errors are written to.
+///
+/// # Returns
+/// - `0` on success, `1` when a name is not an alias or cannot be one.
+pub fn alias(
+    args: &[String],
+    state: &mut ShellState,
+    stdout: &mut dyn Write,
+    stderr: &mut dyn Write,
+) -> i32 {
+    let (list_all, args) = match args.split_first() {
+        Some((flag, rest)) if flag == "-p" => (true, rest),
+        _ => (args.is_empty(), args),
+    };
+    if list_all {
+        let mut names = state.aliases.keys().collect::<Vec<_>>();
+        names.sort();
+        for name in names {
+            if writeln!(stdout, "{}", format_alias(name, &state.aliases[name])).is_err() {
+                return 1;
+            }
+        }
+    }
+
+    let mut status = 0;
+    for arg in args {
+        match arg.split_once('=') {
+            Some((name, value)) if is_alias_name(name) => {
+                state.aliases.insert(name.to_string(), value.to_string());
+            }
+            Some((name, _)) => {
+                write_error(stderr, &format!("alias: `{}': invalid alias name", name));
+                status = 1;
+            }
+            None => match state.aliases.get(arg) {
+                Some(value) => {
+                    if writeln!(stdout, "{}", format_alias(arg, value)).is_err() {
+                        return 1;
+                    }
+                }
+                None => {
+                    write_error(stderr, &format!("alias: {}: not found", arg));
+                    status = 1;
+                }
+            },
+        }
+    }
+    status
+}
+
+/// Remove aliases.
+///
+/// # Parameters
+/// - `args`: names of the aliases, or `-a` to remove them all.
+/// - `state`: shell state holding the aliases.
+/// - `stderr`: stream errors are written to.
+///
+/// # Returns
+/// - `0` on success, `1` when a name is not an alias, `2` without names.
+pub fn unalias(args: &[String], state: &mut ShellState, stderr: &mut dyn Write) -> i32 {
+    if args.first().map(String::as_str) == Some("-a") {
+        state.aliases.clear();
+        return 0;
+    }
+    if args.is_empty() {
+        write_error(stderr, "unalias: usage: unalias [-a] name [name ...]");
+        return 2;
+    }
+    let mut status = 0;
+    for name in args {
+        if state.aliases.remove(name).is_none() {
+            write_error(stderr, &format!("unalias: {}: not found", name));
+            status = 1;
+        }
+    }
+    status
+}
+
+/// Complete the names of the aliases starting with `word`.
+fn complete_aliases(word: &str, state: &ShellState) -> Vec<Candidate> {
+    let mut names = state
+        .aliases
+        .keys()
+        .filter(|name| name.starts_with(word))
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+        .into_iter()
+        .map(|name| Candidate {
+            text: escape(name),
+            display: name.clone(),
+        })
+        .collect()
+}
+
+/// `alias` builtin, see [`alias`].
+pub struct AliasBuiltin;
+
+impl Builtin for AliasBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        alias(args, state, io.stdout, io.stderr)
+    }
+
+    fn flags(&self) -> &str {
+        "p"
+    }
+
+    /// Alias names, to print their value or define them again.
+    fn complete(
+        &self,
+        _args: &[String],
+        word: &str,
+        state: &mut ShellState,
+    ) -> Option<Vec<Candidate>> {
+        Some(complete_aliases(word, state))
+    }
+}
+
+/// `unalias` builtin, see [`unalias`].
+pub struct UnaliasBuiltin;
+
+impl Builtin for UnaliasBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        unalias(args, state, io.stderr)
+    }
+
+    fn flags(&self) -> &str {
+        "a"
+    }
+
+    fn complete(
+        &self,
+        _args: &[String],
+        word: &str,
+        state: &mut ShellState,
+    ) -> Option<Vec<Candidate>> {
+        Some(complete_aliases(word, state))
+    }
+}
@@ -0,0 +1,144 @@
+use crate::*;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::rc::Rc;
+
+/// Standard streams handed to a builtin, so the caller decides where its
+/// input comes from and where its output goes.
+pub struct Streams<'a> {
+    pub stdin: &'a mut dyn Read,
+    pub stdout: &'a mut dyn Write,
+    pub stderr: &'a mut dyn Write,
+    pub terminal: bool, // `stdout` is a terminal, the only place colours are written to
+}
+
+/// A command executed inside the shell process.
+pub trait Builtin {
+    /// Run the builtin.
+    ///
+    /// # Parameters
+    /// - `args`: arguments passed to the command, without its name.
+    /// - `state`: shell state the builtin may read or change.
+    /// - `io`: streams to read input from and write output to.
+    ///
+    /// # Returns
+    /// - exit status code of the command.
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32;
+
+    /// Single-letter options the builtin accepts, offered by Tab completion
+    /// after a `-`.
+    fn flags(&self) -> &str {
+        ""
+    }
+
+    /// Complete an argument of the builtin, for the arguments that are not
+    /// plain file names.
+    ///
+    /// # Parameters
+    /// - `args`: arguments typed before the one being completed, quotes
+    ///   removed.
+    /// - `word`: the argument typed so far, quotes removed.
+    /// - `state`: shell state, e.g. for the current directory.
+    ///
+    /// # Returns
+    /// - the candidates, or `None` to complete flags and file names.
+    fn complete(
+        &self,
+        _args: &[String],
+        _word: &str,
+        _state: &mut ShellState,
+    ) -> Option<Vec<Candidate>> {
+        None
+    }
+}
+
+impl<F> Builtin for F
+where
+    F: Fn(&[String], &mut ShellState, &mut Streams) -> i32,
+{
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        self(args, state, io)
+    }
+}
+
+/// Builtins known to the shell, keyed by command name.
+#[derive(Clone, Default)]
+pub struct Registry {
+    builtins: HashMap<String, Rc<dyn Builtin>>,
+}
+
+impl Registry {
+    /// Create a registry holding every builtin shipped with the shell.
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::default();
+        registry.register("echo", EchoBuiltin);
+        registry.register("pwd", PwdBuiltin);
+        registry.register("cd", CdBuiltin);
+        registry.register("mv", MvBuiltin);
+        registry.register("cp", CpBuiltin);
+        registry.register("ls", LsBuiltin);
+        registry.register("cat", CatBuiltin);
+        registry.register("rm", RmBuiltin);
+        registry.register("mkdir", MkdirBuiltin);
+        registry.register("history", HistoryBuiltin);
+        registry.register("exit", ExitBuiltin);
+        registry.register("clear", ClearBuiltin);
+        registry.register("export", ExportBuiltin);
+        registry.register("unset", UnsetBuiltin);
+        registry.register("set", SetBuiltin);
+        registry.register("env", EnvBuiltin);
+        registry.register("complete", CompleteBuiltin);
+        registry.register("source", SourceBuiltin);
+        registry.register(".", SourceBuiltin);
+        registry.register("break", BreakBuiltin);
+        registry.register("continue", ContinueBuiltin);
+        registry.register("local", LocalBuiltin);
+        registry.register("return", ReturnBuiltin);
+        registry.register("declare", DeclareBuiltin);
+        registry.register("type", TypeBuiltin);
+        registry.register("alias", AliasBuiltin);
+        registry.register("unalias", UnaliasBuiltin);
+        registry.register("jobs", JobsBuiltin);
+        registry.register("fg", FgBuiltin);
+        registry.register("bg", BgBuiltin);
+        registry.register("wait", WaitBuiltin);
+        registry.register("disown", DisownBuiltin);
+        registry.register("trap", TrapBuiltin);
+        registry
+    }
+
+    /// Add a builtin, replacing any builtin previously registered as `name`.
+    ///
+    /// # Parameters
+    /// - `name`: command name the builtin answers to.
+    /// - `builtin`: the implementation, a type implementing `Builtin` or a
+    ///   closure `Fn(&[String], &mut ShellState, &mut Streams) -> i32`.
+    pub fn register(&mut self, name: &str, builtin: impl Builtin + 'static) {
+        self.builtins.insert(name.to_string(), Rc::new(builtin));
+    }
+
+    /// Remove the builtin registered as `name`.
+    ///
+    /// # Returns
+    /// - `true` if there was such a builtin.
+    pub fn unregister(&mut self, name: &str) -> bool {
+        self.builtins.remove(name).is_some()
+    }
+
+    /// Return the builtin registered as `name`, if any.
+    pub fn get(&self, name: &str) -> Option<Rc<dyn Builtin>> {
+        self.builtins.get(name).cloned()
+    }
+
+    /// Return `true` if a builtin is registered as `name`.
+    pub fn contains(&self, name: &str) -> bool {
+        self.builtins.contains_key(name)
+    }
+
+    /// Return the names of all registered builtins, sorted.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.builtins.keys().map(String::as_str).collect();
+        names.sort();
+        names
+    }
+}
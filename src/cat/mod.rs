@@ -1,43 +1,83 @@
-use crate::print_error;
+use crate::{Builtin, ShellState, Streams, interrupted, write_error};
 use std::{
-    fs, io, path::PathBuf
+    fs::File,
+    io::{self, ErrorKind, Read, Write},
+    path::Path,
 };
 
+/// Size of the blocks read and written at once, between which `Ctrl+C` is
+/// checked.
+const BLOCK_SIZE: usize = 64 * 1024;
+
+/// Copy `input` to `stdout` one block at a time, checking `Ctrl+C` before
+/// each block so an endless input can be stopped.
+///
+/// # Returns
+/// - `0` at the end of `input`, `130` when interrupted, `1` when writing
+///   fails, or the error reading `input`.
+fn copy_blocks(input: &mut dyn Read, stdout: &mut dyn Write) -> io::Result<i32> {
+    let mut block = vec![0; BLOCK_SIZE];
+    loop {
+        if interrupted() {
+            return Ok(130);
+        }
+        match input.read(&mut block) {
+            Ok(0) => return Ok(0),
+            Ok(n) => {
+                if stdout.write_all(&block[..n]).is_err() {
+                    return Ok(1);
+                }
+            }
+            // Checked again at the top of the loop
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+}
+
 /// Print file contents or read from stdin when no arguments are given.
 ///
 /// # Parameters
 /// - `args`: file paths to print (relative to `current_dir`).
 /// - `current_dir`: base directory used to resolve relative paths.
+/// - `stdin`: stream read when no file is given.
+/// - `stdout`: stream the contents are written to.
+/// - `stderr`: stream errors are written to.
 ///
 /// # Returns
-/// - `0` on success, non-zero on error.
-pub fn cat(args: &[String], current_dir: &PathBuf) -> i32 {
-    // If no arguments are provided, read and print from stdin
+/// - `0` on success, `130` when interrupted with `Ctrl+C`, non-zero on
+///   error, which stops at the file it happened on.
+pub fn cat(
+    args: &[String],
+    current_dir: &Path,
+    stdin: &mut dyn Read,
+    stdout: &mut dyn Write,
+    stderr: &mut dyn Write,
+) -> i32 {
+    // If no arguments are provided, copy stdin to stdout
     if args.is_empty() {
-        let stdin = io::stdin();
-        for line_res in stdin.lines() {
-            let line = match line_res {
-                Ok(l) => l,
-                Err(_) => {
-                    return 1;
-                }
-            };
-            println!("{}", line);
-        }
-    } else {
-        let mut result = String::new();
-        // Read and concatenate content of all provided files
-        for arg in args {
-            let path = current_dir.join(arg);
-            match fs::read_to_string(&path) {
-                Ok(content) => result.push_str(&content),
-                Err(e) => {
-                    print_error(&format!("cat: {}: {}", arg, e));
-                    return 1;
-                },
+        return copy_blocks(stdin, stdout).unwrap_or(1);
+    }
+    for arg in args {
+        let path = current_dir.join(arg);
+        let copied = File::open(&path).and_then(|mut file| copy_blocks(&mut file, stdout));
+        match copied {
+            Ok(0) => {}
+            Ok(status) => return status,
+            Err(e) => {
+                write_error(stderr, &format!("cat: {}: {}", arg, e));
+                return 1;
             }
         }
-        print!("{}", result);
     }
     0
-}
\ No newline at end of file
+}
+
+/// `cat` builtin, see [`cat`].
+pub struct CatBuiltin;
+
+impl Builtin for CatBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        cat(args, &state.current_dir, io.stdin, io.stdout, io.stderr)
+    }
+}
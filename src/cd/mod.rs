@@ -1,9 +1,10 @@
 use std::{
     env::{current_dir, set_current_dir},
-    path::PathBuf,
+    io::Write,
+    path::{Path, PathBuf},
 };
 
-use crate::print_error;
+use crate::{Builtin, Candidate, ShellState, Streams, complete_paths, write_error};
 
 /// Change the current working directory.
 ///
@@ -12,28 +13,35 @@ use crate::print_error;
 /// - `history`: mutable reference storing previous directories for `cd -` behavior.
 /// - `current_di`: mutable reference to the current directory path.
 /// - `home`: user's home directory path used for `~` expansion.
+/// - `stderr`: stream errors are written to.
 ///
 /// # Returns
 /// - `0` on success, `1` on failure.
-pub fn cd(tab: &[String], history: &mut PathBuf, current_di: &mut PathBuf, home: &PathBuf) -> i32 {
+pub fn cd(
+    tab: &[String],
+    history: &mut PathBuf,
+    current_di: &mut PathBuf,
+    home: &Path,
+    stderr: &mut dyn Write,
+) -> i32 {
     // Default to home directory if no argument is provided
-    let mut path = tab.get(0).unwrap_or(&home.display().to_string()).clone();
+    let mut path = tab.first().unwrap_or(&home.display().to_string()).clone();
     let mut change = true;
     match path.as_str() {
         "-" => {
             // Switch to previous directory stored in history
             if let Err(err) = set_current_dir(history.clone()) {
                 change = false;
-                print_error(&err.to_string());
+                write_error(stderr, &err.to_string());
             }
         }
-        _ if path.len() != 0 => {
+        _ if !path.is_empty() => {
             if &path[0..1] == "~" {
                 path = home.display().to_string() + &path[1..];
             }
             if let Err(err) = set_current_dir(path) {
                 change = false;
-                print_error(&err.to_string());
+                write_error(stderr, &err.to_string());
             }
         }
         _ => {}
@@ -44,7 +52,7 @@ pub fn cd(tab: &[String], history: &mut PathBuf, current_di: &mut PathBuf, home:
         match current_dir() {
             Ok(dir) => current_di.push(dir),
             Err(err) => {
-                print_error(&err.to_string());
+                write_error(stderr, &err.to_string());
                 return 1;
             }
         }
@@ -53,3 +61,28 @@ pub fn cd(tab: &[String], history: &mut PathBuf, current_di: &mut PathBuf, home:
         1
     }
 }
+
+/// `cd` builtin, see [`cd`].
+pub struct CdBuiltin;
+
+impl Builtin for CdBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        cd(
+            args,
+            &mut state.history_current_dir,
+            &mut state.current_dir,
+            &state.home,
+            io.stderr,
+        )
+    }
+
+    /// Only directories can be changed to.
+    fn complete(
+        &self,
+        _args: &[String],
+        word: &str,
+        state: &mut ShellState,
+    ) -> Option<Vec<Candidate>> {
+        Some(complete_paths(word, state, true))
+    }
+}
@@ -0,0 +1,25 @@
+use crate::{Builtin, ShellState, Streams};
+use std::io::Write;
+
+/// Clear the terminal screen.
+///
+/// # Parameters
+/// - `stdout`: stream the reset sequence is written to.
+///
+/// # Returns
+/// - `0` on success, `1` if writing fails.
+pub fn clear(stdout: &mut dyn Write) -> i32 {
+    match writeln!(stdout, "\x1Bc") {
+        Ok(_) => 0,
+        Err(_) => 1,
+    }
+}
+
+/// `clear` builtin, see [`clear`].
+pub struct ClearBuiltin;
+
+impl Builtin for ClearBuiltin {
+    fn run(&self, _args: &[String], _state: &mut ShellState, io: &mut Streams) -> i32 {
+        clear(io.stdout)
+    }
+}
@@ -0,0 +1,57 @@
+use crate::{Builtin, RESERVED_WORDS, ShellState, Streams, find_program, write_error};
+use std::io::Write;
+
+/// Tell how each name would be run as a command: as an alias, a reserved
+/// word, a function (printed back), a builtin or a program found on `$PATH`.
+///
+/// # Parameters
+/// - `args`: names of the commands.
+/// - `state`: shell state holding the aliases, functions, builtins and
+///   `$PATH`.
+/// - `stdout`: stream the descriptions are printed to.
+/// - `stderr`: stream errors are written to.
+///
+/// # Returns
+/// - `0` on success, `1` when a name is not found.
+pub fn command_type(
+    args: &[String],
+    state: &mut ShellState,
+    stdout: &mut dyn Write,
+    stderr: &mut dyn Write,
+) -> i32 {
+    let mut status = 0;
+    for name in args {
+        let description = if let Some(value) = state.aliases.get(name) {
+            format!("{} is aliased to `{}'", name, value)
+        } else if RESERVED_WORDS.contains(&name.as_str()) {
+            format!("{} is a shell keyword", name)
+        } else if let Some(function) = state.functions.get(name) {
+            format!("{} is a function\n{}", name, function)
+        } else if state.builtins.contains(name) {
+            format!("{} is a shell builtin", name)
+        } else {
+            match find_program(name, state) {
+                Ok(_) if name.contains('/') => format!("{} is {}", name, name),
+                Ok(path) => format!("{} is {}", name, path.display()),
+                Err(_) => {
+                    write_error(stderr, &format!("type: {}: not found", name));
+                    status = 1;
+                    continue;
+                }
+            }
+        };
+        if writeln!(stdout, "{}", description).is_err() {
+            return 1;
+        }
+    }
+    status
+}
+
+/// `type` builtin, see [`command_type`].
+pub struct TypeBuiltin;
+
+impl Builtin for TypeBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        command_type(args, state, io.stdout, io.stderr)
+    }
+}
@@ -0,0 +1,236 @@
+use crate::{
+    Builtin, Candidate, CostumSplit, ShellState, Streams, capture_output, complete_paths, escape,
+    expand_string, quote, write_error,
+};
+use std::io::Write;
+
+/// Run the command of a `-C` or `-F` spec with the command name, the word
+/// and the word before it as arguments.
+///
+/// # Returns
+/// - the lines it printed, none when it cannot run.
+fn completer_output(
+    command: &str,
+    name: &str,
+    word: &str,
+    previous: &str,
+    state: &mut ShellState,
+) -> Vec<String> {
+    let line = format!(
+        "{} {} {} {}",
+        command,
+        quote(name),
+        quote(word),
+        quote(previous)
+    );
+    match line.custom_split() {
+        Ok(list) => match capture_output(&list, state) {
+            Ok((output, _)) => output.lines().map(str::to_string).collect(),
+            Err(_) => Vec::new(),
+        },
+        Err(_) => Vec::new(),
+    }
+}
+
+/// How `complete` tells to complete the arguments of a command.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompletionSpec {
+    Words(String),    // `-W list`: words of the list, expanded when completing
+    Command(String),  // `-C command`: lines printed by the command
+    Function(String), // `-F function`: lines printed by the shell function
+    Directories,      // `-d`: directory names
+    Files,            // `-f`: file names
+}
+
+impl CompletionSpec {
+    /// Compute the candidates for the argument `word` of the command `name`.
+    /// A `-C` command or `-F` function runs with the command name, the word
+    /// and the word before it as arguments, like bash does. Without arrays
+    /// for `COMPREPLY`, the lines it prints are the candidates.
+    ///
+    /// # Parameters
+    /// - `name`: the command being completed.
+    /// - `word`: the argument typed so far, quotes removed.
+    /// - `previous`: the word before it.
+    /// - `state`: shell state the word list is expanded with.
+    pub fn candidates(
+        &self,
+        name: &str,
+        word: &str,
+        previous: &str,
+        state: &mut ShellState,
+    ) -> Vec<Candidate> {
+        let words = match self {
+            CompletionSpec::Directories => return complete_paths(word, state, true),
+            CompletionSpec::Files => return complete_paths(word, state, false),
+            CompletionSpec::Words(list) => match expand_string(list, state) {
+                Ok(words) => words.split_whitespace().map(str::to_string).collect(),
+                Err(_) => Vec::new(),
+            },
+            CompletionSpec::Command(command) => {
+                completer_output(command, name, word, previous, state)
+            }
+            CompletionSpec::Function(function) if state.functions.contains_key(function) => {
+                completer_output(&quote(function), name, word, previous, state)
+            }
+            CompletionSpec::Function(_) => Vec::new(),
+        };
+        let mut words = words
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(word))
+            .collect::<Vec<_>>();
+        words.dedup();
+        words
+            .into_iter()
+            .map(|candidate| Candidate {
+                text: escape(&candidate),
+                display: candidate,
+            })
+            .collect()
+    }
+
+    /// Return the `complete` command registering this spec for `name`.
+    fn command_line(&self, name: &str) -> String {
+        match self {
+            CompletionSpec::Words(list) => format!("complete -W {} {}", quote(list), name),
+            CompletionSpec::Command(command) => {
+                format!("complete -C {} {}", quote(command), name)
+            }
+            CompletionSpec::Function(function) => {
+                format!("complete -F {} {}", quote(function), name)
+            }
+            CompletionSpec::Directories => format!("complete -d {}", name),
+            CompletionSpec::Files => format!("complete -f {}", name),
+        }
+    }
+}
+
+/// Print, register or remove how the arguments of commands are completed.
+///
+/// # Parameters
+/// - `args`: `-W wordlist`, `-C command`, `-F function`, `-d` or `-f`
+///   followed by the names of the commands to register it for; `-r`
+///   followed by names to remove (every spec without names); `-p` or
+///   nothing, optionally followed by names, to print the specs as
+///   `complete` commands.
+/// - `state`: shell state holding the completion specs.
+/// - `stdout`: stream the specs are printed to.
+/// - `stderr`: stream errors are written to.
+///
+/// # Returns
+/// - `0` on success, `1` when a name has no spec, `2` for an invalid
+///   option or a missing argument.
+pub fn complete(
+    args: &[String],
+    state: &mut ShellState,
+    stdout: &mut dyn Write,
+    stderr: &mut dyn Write,
+) -> i32 {
+    let usage = |stderr: &mut dyn Write| {
+        write_error(
+            stderr,
+            "complete: usage: complete [-pr] [-df] [-W wordlist] [-C command] [-F function] [name ...]",
+        );
+        2
+    };
+
+    let mut spec = None;
+    let mut remove = false;
+    let mut i = 0;
+    while let Some(arg) = args.get(i) {
+        match arg.as_str() {
+            "-W" | "-C" | "-F" => {
+                let Some(value) = args.get(i + 1) else {
+                    write_error(
+                        stderr,
+                        &format!("complete: {}: option requires an argument", arg),
+                    );
+                    return usage(stderr);
+                };
+                spec = Some(match arg.as_str() {
+                    "-W" => CompletionSpec::Words(value.clone()),
+                    "-C" => CompletionSpec::Command(value.clone()),
+                    _ => CompletionSpec::Function(value.clone()),
+                });
+                i += 1;
+            }
+            "-d" => spec = Some(CompletionSpec::Directories),
+            "-f" => spec = Some(CompletionSpec::Files),
+            "-r" => remove = true,
+            "-p" => {}
+            "--" => {
+                i += 1;
+                break;
+            }
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                write_error(stderr, &format!("complete: {}: invalid option", arg));
+                return usage(stderr);
+            }
+            _ => break,
+        }
+        i += 1;
+    }
+    let names = &args[i..];
+
+    if remove {
+        if names.is_empty() {
+            state.completions.clear();
+            return 0;
+        }
+        let mut status = 0;
+        for name in names {
+            if state.completions.remove(name).is_none() {
+                let message = format!("complete: {}: no completion specification", name);
+                write_error(stderr, &message);
+                status = 1;
+            }
+        }
+        return status;
+    }
+
+    if let Some(spec) = spec {
+        if names.is_empty() {
+            return usage(stderr);
+        }
+        for name in names {
+            state.completions.insert(name.clone(), spec.clone());
+        }
+        return 0;
+    }
+
+    let mut status = 0;
+    let mut listed = if names.is_empty() {
+        state.completions.keys().cloned().collect::<Vec<_>>()
+    } else {
+        names.to_vec()
+    };
+    listed.sort();
+    for name in listed {
+        match state.completions.get(&name) {
+            Some(spec) => {
+                if writeln!(stdout, "{}", spec.command_line(&name)).is_err() {
+                    return 1;
+                }
+            }
+            None => {
+                let message = format!("complete: {}: no completion specification", name);
+                write_error(stderr, &message);
+                status = 1;
+            }
+        }
+    }
+    status
+}
+
+/// `complete` builtin, see [`complete`].
+pub struct CompleteBuiltin;
+
+impl Builtin for CompleteBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        complete(args, state, io.stdout, io.stderr)
+    }
+
+    fn flags(&self) -> &str {
+        "prdfWCF"
+    }
+}
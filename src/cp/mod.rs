@@ -1,35 +1,66 @@
-use crate::print_error;
-use std::fs;
+use crate::{Builtin, ShellState, Streams, interrupted, write_error};
+use std::fs::File;
+use std::io;
+use std::io::ErrorKind;
+use std::io::{Read, Write};
 use std::path::Path;
 
+/// Copy the contents and permissions of the file `src` to `dst` one block at
+/// a time, so that `Ctrl+C` stops a long copy.
+///
+/// # Returns
+/// - `Ok(())` once copied, an `Interrupted` error when stopped by `Ctrl+C`,
+///   or the error that stopped the copy.
+fn copy_file(src: &Path, dst: &Path) -> io::Result<()> {
+    let mut input = File::open(src)?;
+    let mut output = File::create(dst)?;
+    let mut block = vec![0; 64 * 1024];
+    loop {
+        if interrupted() {
+            return Err(io::Error::from(ErrorKind::Interrupted));
+        }
+        match input.read(&mut block) {
+            Ok(0) => break,
+            Ok(n) => output.write_all(&block[..n])?,
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    output.set_permissions(input.metadata()?.permissions())
+}
+
 /// Copy files to a destination. When multiple sources are provided the
 /// destination must be a directory.
 ///
 /// # Parameters
 /// - `args`: list of source paths followed by destination path.
+/// - `current_dir`: base directory for relative paths.
+/// - `stderr`: stream errors are written to.
 ///
 /// # Returns
-/// - `0` on success, non-zero on errors.
-pub fn cp(args: &[String]) -> i32 {
+/// - `0` on success, `130` when interrupted with `Ctrl+C`, non-zero on
+///   errors.
+pub fn cp(args: &[String], current_dir: &Path, stderr: &mut dyn Write) -> i32 {
     // Check if sufficient arguments are provided
     if args.len() < 2 {
-        print_error("cp: wrong number of arguments");
+        write_error(stderr, "cp: wrong number of arguments");
         return 1;
     }
-    let dst = Path::new(&args[args.len() - 1]);
+    let dst_str = &args[args.len() - 1];
+    let dst = current_dir.join(dst_str);
     // Validate that destination is a directory when copying multiple files
     if args.len() > 2 && !dst.is_dir() {
-        print_error(&format!("cp: target '{}' is not a directory", dst.display()));
+        write_error(stderr, &format!("cp: target '{}' is not a directory", dst_str));
         return 1;
     }
     for src_str in &args[..args.len() - 1] {
-        let src = Path::new(src_str);
+        let src = current_dir.join(src_str);
         if !src.exists() {
-            print_error(&format!("cp: cannot stat '{}': No such file or directory", src.display()));
+            write_error(stderr, &format!("cp: cannot stat '{}': No such file or directory", src_str));
             continue;
         }
         if src.is_dir() {
-            print_error(&format!("cp: -r not specified; omitting directory '{}'", src.display()));
+            write_error(stderr, &format!("cp: -r not specified; omitting directory '{}'", src_str));
             continue;
         }
         let final_dst = if dst.is_dir() {
@@ -37,9 +68,23 @@ pub fn cp(args: &[String]) -> i32 {
         } else {
             dst.to_path_buf()
         };
-        if let Err(err) = fs::copy(src, &final_dst) {
-            print_error(&format!("cp: cannot copy '{}': {}", src.display(), err));
+        match copy_file(&src, &final_dst) {
+            Ok(()) => {}
+            Err(_) if interrupted() => return 130,
+            Err(err) => {
+                let message = format!("cp: cannot copy '{}': {}", src_str, err);
+                write_error(stderr, &message);
+            }
         }
     }
     0
-}
\ No newline at end of file
+}
+
+/// `cp` builtin, see [`cp`].
+pub struct CpBuiltin;
+
+impl Builtin for CpBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        cp(args, &state.current_dir, io.stderr)
+    }
+}
@@ -1,26 +1,38 @@
+use crate::{Builtin, ShellState, Streams};
+use std::io::Write;
+
 /// Echo the provided arguments to stdout, interpreting escape sequences.
 ///
 /// # Parameters
 /// - `args`: slice of argument strings to join and print.
+/// - `stdout`: stream the text is written to.
 ///
 /// # Returns
-/// - `0` on success.
-pub fn echo(args: &[String]) -> i32 {
+/// - `0` on success, `1` if writing fails.
+pub fn echo(args: &[String], stdout: &mut dyn Write) -> i32 {
     let entry = args.join(" ");
 
     let (content, newline) = parse_entry(&entry);
-    if newline {
-        println!("{content}");
+    let res = if newline {
+        writeln!(stdout, "{content}")
     } else {
-        print!("{content}")
+        write!(stdout, "{content}")
+    };
+    match res {
+        Ok(_) => 0,
+        Err(_) => 1,
     }
-    0
 }
 
 /// Parse a single string for backslash-escaped sequences used by `echo`.
 ///
 /// Returns a tuple `(output, newline)` where `output` is the processed
 /// string and `newline` is `true` if a trailing newline should be printed.
+#[allow(
+    clippy::single_match,
+    clippy::collapsible_match,
+    clippy::is_digit_ascii_radix
+)]
 fn parse_entry(entry: &str) -> (String, bool) {
     let mut result = String::new();
     let mut chars = entry.chars().peekable();
@@ -138,3 +150,12 @@ fn parse_entry(entry: &str) -> (String, bool) {
 //             \\      Output a backslash.
 
 //             All other backslash sequences elicit undefined behaviour.
+
+/// `echo` builtin, see [`echo`].
+pub struct EchoBuiltin;
+
+impl Builtin for EchoBuiltin {
+    fn run(&self, args: &[String], _state: &mut ShellState, io: &mut Streams) -> i32 {
+        echo(args, io.stdout)
+    }
+}
@@ -0,0 +1,325 @@
+use crate::{
+    ShellState, UNSAFE_CHARACTERS, compare_names, is_executable, is_name, split_assignment,
+};
+use std::fs;
+use std::path::PathBuf;
+use users::get_user_by_name;
+use users::os::unix::UserExt;
+
+/// A possible completion of the word under the cursor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub text: String,    // Replacement for the whole word, escaped for the shell
+    pub display: String, // Name shown when the candidates are listed
+}
+
+impl Candidate {
+    /// Return `true` when the candidate can be followed by more text, like a
+    /// directory: no space is added after it.
+    pub fn is_partial(&self) -> bool {
+        self.text.ends_with('/')
+    }
+}
+
+/// The word under the cursor and what comes before it in its command.
+#[derive(Debug, Default)]
+pub struct Word {
+    pub start: usize,        // Position of the word in the line, in characters
+    pub text: String,        // The word, quotes and backslashes removed
+    pub before: Vec<String>, // Previous words of the command, the same way
+    pub redirect: bool,      // `true` when the word is the target of a redirection
+}
+
+impl Word {
+    /// Return `true` when the word is the name of the command to run.
+    pub fn is_command(&self) -> bool {
+        !self.redirect && self.before.iter().all(|w| split_assignment(w).is_some())
+    }
+
+    /// Return the arguments of the command typed before the word.
+    pub fn args(&self) -> &[String] {
+        let start = self
+            .before
+            .iter()
+            .position(|w| split_assignment(w).is_none())
+            .map_or(self.before.len(), |i| i + 1);
+        &self.before[start..]
+    }
+
+    /// Return the name of the command the word is an argument of.
+    pub fn command(&self) -> Option<&str> {
+        self.before
+            .iter()
+            .find(|w| split_assignment(w).is_none())
+            .map(String::as_str)
+    }
+}
+
+/// Reserved words that are followed by a command, like `then` in
+/// `if true; then ls`.
+const COMMAND_PREFIXES: &[&str] = &[
+    "!", "do", "elif", "else", "if", "then", "until", "while", "{",
+];
+
+/// Find the word ending at `cursor`, splitting the line the way the parser
+/// does: on unquoted blanks and operators.
+pub fn current_word(chars: &[char], cursor: usize) -> Word {
+    let mut word = Word {
+        start: cursor,
+        ..Word::default()
+    };
+    let mut text = String::new();
+    let mut start = None;
+    let mut quote = None;
+    let mut redirect = false;
+
+    let mut i = 0;
+    while i < cursor {
+        let ch = chars[i];
+        match (quote, ch) {
+            (Some(q), _) if ch == q => quote = None,
+            (Some('"'), '\\') if i + 1 < cursor && "$`\"\\".contains(chars[i + 1]) => {
+                i += 1;
+                text.push(chars[i]);
+            }
+            (Some(_), _) => text.push(ch),
+            (None, '\\') if i + 1 < cursor => {
+                start.get_or_insert(i);
+                i += 1;
+                text.push(chars[i]);
+            }
+            (None, '\'' | '"') => {
+                start.get_or_insert(i);
+                quote = Some(ch);
+            }
+            (None, _) if ch.is_whitespace() || "|&;()<>".contains(ch) => {
+                if start.take().is_some() {
+                    let done = std::mem::take(&mut text);
+                    let prefix =
+                        word.before.is_empty() && COMMAND_PREFIXES.contains(&done.as_str());
+                    if !std::mem::take(&mut redirect) && !prefix {
+                        word.before.push(done);
+                    }
+                }
+                match ch {
+                    '<' | '>' => redirect = true,
+                    '|' | '&' | ';' | '(' | ')' | '\n' => word.before.clear(),
+                    _ => {}
+                }
+            }
+            (None, _) => {
+                start.get_or_insert(i);
+                text.push(ch);
+            }
+        }
+        i += 1;
+    }
+
+    if let Some(start) = start {
+        word.start = start;
+    }
+    word.text = text;
+    word.redirect = redirect;
+    word
+}
+
+/// Escape `text` with backslashes so the shell reads it back as is, for
+/// the characters `ls` quotes names for.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for ch in text.chars() {
+        if UNSAFE_CHARACTERS.contains(ch) || ch == '\'' {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+/// Complete `prefix` with the names of the variables.
+pub fn complete_variables(prefix: &str, state: &ShellState) -> Vec<Candidate> {
+    let (braced, name) = match prefix.strip_prefix("${") {
+        Some(name) => (true, name),
+        None => (false, &prefix[1..]),
+    };
+    let mut names = state
+        .vars
+        .keys()
+        .filter(|var| var.starts_with(name))
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+        .into_iter()
+        .map(|var| Candidate {
+            text: if braced {
+                format!("${{{}}}", var)
+            } else {
+                format!("${}", var)
+            },
+            display: var.clone(),
+        })
+        .collect()
+}
+
+/// Complete `prefix` with the names of the aliases, of the functions, of the
+/// builtins and of the executables found on `$PATH`.
+pub fn complete_commands(prefix: &str, state: &ShellState) -> Vec<Candidate> {
+    let mut names = state
+        .builtins
+        .names()
+        .into_iter()
+        .chain(state.functions.keys().map(String::as_str))
+        .chain(state.aliases.keys().map(String::as_str))
+        .filter(|name| name.starts_with(prefix))
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    let paths = state
+        .get_var("PATH")
+        .unwrap_or("/usr/local/bin:/usr/bin:/bin");
+    for dir in paths.split(':').filter(|dir| !dir.is_empty()) {
+        let Ok(entries) = fs::read_dir(state.current_dir.join(dir)) else {
+            continue;
+        };
+        for entry in entries.filter_map(Result::ok) {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let path = entry.path();
+            if name.starts_with(prefix) && path.is_file() && is_executable(&path) {
+                names.push(name);
+            }
+        }
+    }
+    names.sort();
+    names.dedup();
+    names
+        .into_iter()
+        .map(|name| Candidate {
+            text: escape(&name),
+            display: name,
+        })
+        .collect()
+}
+
+/// Complete `prefix` with the single-letter `flags` of a command.
+pub fn complete_flags(prefix: &str, flags: &str) -> Vec<Candidate> {
+    flags
+        .chars()
+        .map(|flag| format!("-{}", flag))
+        .filter(|flag| flag.starts_with(prefix))
+        .map(|flag| Candidate {
+            text: flag.clone(),
+            display: flag,
+        })
+        .collect()
+}
+
+/// Complete `prefix` with the paths it may start, relative to the current
+/// directory of the shell. Hidden files are only offered when the name
+/// being completed starts with a `.`.
+///
+/// # Parameters
+/// - `prefix`: path typed so far, unquoted.
+/// - `state`: shell state, for the current directory and `$HOME`.
+/// - `dirs_only`: only offer directories.
+pub fn complete_paths(prefix: &str, state: &ShellState, dirs_only: bool) -> Vec<Candidate> {
+    let (dir, base) = match prefix.rfind('/') {
+        Some(i) => (&prefix[..=i], &prefix[i + 1..]),
+        None => ("", prefix),
+    };
+
+    // A leading `~` or `~user` is kept as typed but read as the home directory
+    let (home, rest) = match dir.strip_prefix('~') {
+        Some(rest) => {
+            let (user, rest) = rest.split_once('/').unwrap_or((rest, ""));
+            let home = if user.is_empty() {
+                match state.get_var("HOME") {
+                    Some(home) => PathBuf::from(home),
+                    None => state.home.clone(),
+                }
+            } else {
+                match get_user_by_name(user) {
+                    Some(user) => user.home_dir().to_path_buf(),
+                    None => return Vec::new(),
+                }
+            };
+            (Some((format!("~{}/", user), home)), rest)
+        }
+        None => (None, dir),
+    };
+    let (typed, path) = match &home {
+        Some((typed, home)) => (typed.clone() + &escape(rest), home.join(rest)),
+        None => (escape(rest), state.current_dir.join(rest)),
+    };
+
+    let Ok(entries) = fs::read_dir(&path) else {
+        return Vec::new();
+    };
+    let mut candidates = Vec::new();
+    for entry in entries.filter_map(Result::ok) {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !name.starts_with(base) || (name.starts_with('.') && !base.starts_with('.')) {
+            continue;
+        }
+        let is_dir = entry.path().is_dir();
+        if dirs_only && !is_dir {
+            continue;
+        }
+        let slash = if is_dir { "/" } else { "" };
+        candidates.push(Candidate {
+            text: typed.clone() + &escape(&name) + slash,
+            display: name + slash,
+        });
+    }
+    candidates.sort_by(|a, b| compare_names(&a.display, &b.display));
+    candidates
+}
+
+/// Complete an argument of a command: with the spec registered by
+/// `complete` for the command, else with the completion hook of a builtin,
+/// else with the flags of a builtin after a `-`, else with file names.
+fn complete_argument(word: &Word, state: &mut ShellState) -> Vec<Candidate> {
+    let prefix = word.text.as_str();
+    let Some(name) = word.command().filter(|_| !word.redirect) else {
+        return complete_paths(prefix, state, false);
+    };
+    if let Some(spec) = state.completions.get(name).cloned() {
+        let previous = word.before.last().map_or("", String::as_str);
+        return spec.candidates(name, prefix, previous, state);
+    }
+    let Some(builtin) = state.builtins.get(name) else {
+        return complete_paths(prefix, state, false);
+    };
+    if let Some(candidates) = builtin.complete(word.args(), prefix, state) {
+        candidates
+    } else if prefix.starts_with('-') && !builtin.flags().is_empty() {
+        complete_flags(prefix, builtin.flags())
+    } else {
+        complete_paths(prefix, state, false)
+    }
+}
+
+/// Complete the word ending at `cursor`: variables after a `$`, commands
+/// in command position, and arguments otherwise, see [`complete_argument`].
+///
+/// # Returns
+/// - the word being completed and its candidates.
+pub fn complete_line(
+    chars: &[char],
+    cursor: usize,
+    state: &mut ShellState,
+) -> (Word, Vec<Candidate>) {
+    let word = current_word(chars, cursor);
+    let prefix = word.text.as_str();
+    let variable = match prefix.strip_prefix("${").or(prefix.strip_prefix('$')) {
+        Some(name) => name.is_empty() || is_name(name),
+        None => false,
+    };
+
+    let candidates = if variable {
+        complete_variables(prefix, state)
+    } else if word.is_command() && !prefix.contains('/') {
+        complete_commands(prefix, state)
+    } else {
+        complete_argument(&word, state)
+    };
+    (word, candidates)
+}
@@ -0,0 +1,635 @@
+use crate::{ShellState, format_grid};
+use std::io;
+use std::io::ErrorKind;
+use std::io::Write;
+use std::mem::MaybeUninit;
+use term_size::dimensions;
+
+pub use completion::*;
+use search::*;
+pub mod completion;
+mod search;
+
+/// Number of candidates above which completion asks before listing them.
+const LIST_QUERY_ITEMS: usize = 100;
+
+/// Read one line from stdin, including its trailing newline.
+///
+/// Reads byte by byte straight from file descriptor 0 so nothing past the
+/// newline is buffered: forked commands reading stdin must see the rest of
+/// the input.
+///
+/// # Returns
+/// - number of bytes read, `0` at end of file.
+pub fn read_plain_line(buf: &mut String) -> io::Result<usize> {
+    let mut bytes = Vec::new();
+    while let Some(byte) = read_byte()? {
+        bytes.push(byte);
+        if byte == b'\n' {
+            break;
+        }
+    }
+    buf.push_str(&String::from_utf8_lossy(&bytes));
+    Ok(bytes.len())
+}
+
+/// Read a single byte from file descriptor 0, retrying on `EINTR`.
+///
+/// # Returns
+/// - the byte, or `None` at end of file.
+fn read_byte() -> io::Result<Option<u8>> {
+    let mut byte = 0u8;
+    loop {
+        let n = unsafe { libc::read(0, &mut byte as *mut u8 as *mut libc::c_void, 1) };
+        match n {
+            -1 => {
+                let err = io::Error::last_os_error();
+                if err.kind() != ErrorKind::Interrupted {
+                    return Err(err);
+                }
+            }
+            0 => return Ok(None),
+            _ => return Ok(Some(byte)),
+        }
+    }
+}
+
+/// Return `true` when a byte can be read from stdin within `timeout` ms,
+/// used to tell a lone Escape from the start of an escape sequence.
+fn byte_ready(timeout: i32) -> bool {
+    let mut fd = libc::pollfd {
+        fd: 0,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    unsafe { libc::poll(&mut fd, 1, timeout) > 0 }
+}
+
+/// Terminal switched to raw mode; the original settings are restored when
+/// it is dropped, whichever way the line editing ends.
+struct RawMode {
+    original: libc::termios,
+}
+
+impl RawMode {
+    /// Turn off canonical mode, echo, signals and flow control on stdin.
+    fn enable() -> io::Result<RawMode> {
+        let mut original = MaybeUninit::<libc::termios>::uninit();
+        if unsafe { libc::tcgetattr(0, original.as_mut_ptr()) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        let original = unsafe { original.assume_init() };
+        let mut raw = original;
+        raw.c_iflag &= !(libc::BRKINT | libc::ICRNL | libc::INPCK | libc::ISTRIP | libc::IXON);
+        raw.c_lflag &= !(libc::ECHO | libc::ICANON | libc::IEXTEN | libc::ISIG);
+        raw.c_cflag |= libc::CS8;
+        raw.c_cc[libc::VMIN] = 1;
+        raw.c_cc[libc::VTIME] = 0;
+        if unsafe { libc::tcsetattr(0, libc::TCSADRAIN, &raw) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(RawMode { original })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        unsafe { libc::tcsetattr(0, libc::TCSADRAIN, &self.original) };
+    }
+}
+
+/// A key press decoded from the terminal input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Key {
+    Char(char), // Printable character
+    Ctrl(char), // Control key, e.g. `Ctrl('a')` for Ctrl-A
+    Alt(char),  // Escape followed by a character, e.g. Alt-B
+    Enter,
+    Tab,
+    Backspace,
+    AltBackspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    WordLeft,  // Ctrl-Left or Alt-Left
+    WordRight, // Ctrl-Right or Alt-Right
+    Escape,
+    Eof,
+    Unknown,
+}
+
+/// Read and decode the next key press.
+fn read_key() -> io::Result<Key> {
+    let Some(byte) = read_byte()? else {
+        return Ok(Key::Eof);
+    };
+    let key = match byte {
+        b'\r' | b'\n' => Key::Enter,
+        b'\t' => Key::Tab,
+        0x7f | 0x08 => Key::Backspace,
+        0x1b => read_escape()?,
+        1..=26 => Key::Ctrl((b'a' + byte - 1) as char),
+        0..=31 => Key::Unknown,
+        _ => read_utf8(byte)?,
+    };
+    Ok(key)
+}
+
+/// Decode the rest of a UTF-8 character starting with `first`.
+fn read_utf8(first: u8) -> io::Result<Key> {
+    let len = match first {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => 1,
+    };
+    let mut bytes = vec![first];
+    while bytes.len() < len {
+        match read_byte()? {
+            Some(byte) => bytes.push(byte),
+            None => break,
+        }
+    }
+    Ok(
+        match std::str::from_utf8(&bytes)
+            .ok()
+            .and_then(|s| s.chars().next())
+        {
+            Some(ch) => Key::Char(ch),
+            None => Key::Unknown,
+        },
+    )
+}
+
+/// Decode what follows an Escape byte: a CSI (`ESC [`) or SS3 (`ESC O`)
+/// sequence sent by special keys, or a character typed with Alt.
+fn read_escape() -> io::Result<Key> {
+    if !byte_ready(50) {
+        return Ok(Key::Escape);
+    }
+    let Some(byte) = read_byte()? else {
+        return Ok(Key::Escape);
+    };
+    match byte {
+        b'[' => {}
+        b'O' => {
+            return Ok(match read_byte()? {
+                Some(b'A') => Key::Up,
+                Some(b'B') => Key::Down,
+                Some(b'C') => Key::Right,
+                Some(b'D') => Key::Left,
+                Some(b'H') => Key::Home,
+                Some(b'F') => Key::End,
+                _ => Key::Unknown,
+            });
+        }
+        0x7f | 0x08 => return Ok(Key::AltBackspace),
+        0x1b => return Ok(Key::Escape),
+        _ => {
+            return Ok(match read_utf8(byte)? {
+                Key::Char(ch) => Key::Alt(ch.to_ascii_lowercase()),
+                key => key,
+            });
+        }
+    }
+
+    // Parameters up to the final byte, e.g. `1;5` in `ESC [ 1 ; 5 C`
+    let mut params = String::new();
+    let last = loop {
+        match read_byte()? {
+            Some(byte @ 0x40..=0x7e) => break byte,
+            Some(byte) => params.push(byte as char),
+            None => return Ok(Key::Unknown),
+        }
+    };
+    let modified = params.contains(';');
+    Ok(match (last, params.as_str()) {
+        (b'A', _) => Key::Up,
+        (b'B', _) => Key::Down,
+        (b'C', _) if modified => Key::WordRight,
+        (b'D', _) if modified => Key::WordLeft,
+        (b'C', _) => Key::Right,
+        (b'D', _) => Key::Left,
+        (b'H', _) | (b'~', "1" | "7") => Key::Home,
+        (b'F', _) | (b'~', "4" | "8") => Key::End,
+        (b'~', "3") => Key::Delete,
+        _ => Key::Unknown,
+    })
+}
+
+/// Return `true` for characters making up a word for Alt-B, Alt-F and Alt-D.
+fn is_word(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+/// Number of terminal columns `ch` takes once displayed; control characters
+/// are shown in caret notation, e.g. `^J` for a newline of a recalled entry.
+fn char_width(ch: char) -> usize {
+    if ch.is_ascii_control() { 2 } else { 1 }
+}
+
+/// Number of terminal columns `text` takes once displayed, skipping ANSI
+/// escape sequences and the `\x01`/`\x02` markers around non-printing text.
+pub fn display_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut chars = text.chars();
+    let mut hidden = false;
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\x01' => hidden = true,
+            '\x02' => hidden = false,
+            '\x1b' => {
+                if chars.next() == Some('[') {
+                    for ch in chars.by_ref() {
+                        if ('\x40'..='\x7e').contains(&ch) {
+                            break;
+                        }
+                    }
+                }
+            }
+            _ if hidden => {}
+            _ => width += char_width(ch),
+        }
+    }
+    width
+}
+
+/// Line being edited, with what is needed to redraw it.
+struct Line {
+    buf: Vec<char>,       // Characters typed so far
+    cursor: usize,        // Position of the cursor in `buf`
+    head: Option<String>, // Lines of the prompt before its last one
+    prompt: String,       // Last line of the prompt, redrawn with the buffer
+    prompt_width: usize,
+    row: usize, // Terminal row of the cursor, counted from the prompt
+    highlight: Option<(usize, usize)>, // Part of `buf` shown in reverse video
+}
+
+impl Line {
+    /// Replace the whole buffer and put the cursor at its end.
+    fn set(&mut self, text: &str) {
+        self.buf = text.chars().collect();
+        self.cursor = self.buf.len();
+    }
+
+    /// Insert `text` at the cursor.
+    fn insert(&mut self, text: &[char]) {
+        self.buf
+            .splice(self.cursor..self.cursor, text.iter().copied());
+        self.cursor += text.len();
+    }
+
+    /// Replace the characters from `from` up to the cursor with `text`.
+    fn replace(&mut self, from: usize, text: &str) {
+        let text = text.chars().collect::<Vec<_>>();
+        self.buf.splice(from..self.cursor, text.iter().copied());
+        self.cursor = from + text.len();
+    }
+
+    /// Remove the characters between `from` and `to` and return them.
+    fn remove(&mut self, from: usize, to: usize) -> String {
+        self.cursor = from;
+        self.buf.drain(from..to).collect()
+    }
+
+    /// Start of the word before the cursor.
+    fn word_left(&self) -> usize {
+        let mut i = self.cursor;
+        while i > 0 && !is_word(self.buf[i - 1]) {
+            i -= 1;
+        }
+        while i > 0 && is_word(self.buf[i - 1]) {
+            i -= 1;
+        }
+        i
+    }
+
+    /// End of the word after the cursor.
+    fn word_right(&self) -> usize {
+        let mut i = self.cursor;
+        while i < self.buf.len() && !is_word(self.buf[i]) {
+            i += 1;
+        }
+        while i < self.buf.len() && is_word(self.buf[i]) {
+            i += 1;
+        }
+        i
+    }
+
+    /// Start of the blank-delimited word before the cursor, for Ctrl-W.
+    fn blank_word_left(&self) -> usize {
+        let mut i = self.cursor;
+        while i > 0 && self.buf[i - 1].is_whitespace() {
+            i -= 1;
+        }
+        while i > 0 && !self.buf[i - 1].is_whitespace() {
+            i -= 1;
+        }
+        i
+    }
+
+    /// Print the whole prompt on a fresh row, then the buffer.
+    fn redraw(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        if let Some(head) = &self.head {
+            writeln!(out, "{}", head)?;
+        }
+        self.row = 0;
+        self.refresh(out)
+    }
+
+    /// Print `text` below the line being edited, then draw the line again.
+    fn print_below(&mut self, text: &str, out: &mut dyn Write) -> io::Result<()> {
+        let cursor = self.cursor;
+        self.cursor = self.buf.len();
+        self.refresh(out)?;
+        write!(out, "\n{}", text)?;
+        self.cursor = cursor;
+        self.redraw(out)
+    }
+
+    /// Redraw the prompt and the buffer, then move the terminal cursor to
+    /// the editing position. Long lines wrap, so the drawing starts by going
+    /// back up to the row of the prompt.
+    fn refresh(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        let cols = dimensions().map(|(w, _)| w).unwrap_or(80).max(1);
+        let mut text = String::new();
+        if self.row > 0 {
+            text.push_str(&format!("\x1b[{}A", self.row));
+        }
+        text.push_str("\r\x1b[J");
+        text.push_str(&self.prompt);
+        for (i, &ch) in self.buf.iter().enumerate() {
+            match self.highlight {
+                Some((start, _)) if start == i => text.push_str("\x1b[7m"),
+                Some((_, end)) if end == i => text.push_str("\x1b[27m"),
+                _ => {}
+            }
+            if ch.is_ascii_control() {
+                text.push('^');
+                text.push(((ch as u8) ^ 0x40) as char);
+            } else {
+                text.push(ch);
+            }
+        }
+        if self.highlight.is_some_and(|(_, end)| end >= self.buf.len()) {
+            text.push_str("\x1b[27m");
+        }
+
+        let width = |chars: &[char]| chars.iter().map(|&c| char_width(c)).sum::<usize>();
+        let end = self.prompt_width + width(&self.buf);
+        // A line filling the last column leaves the cursor there: go to the next row
+        if end > 0 && end.is_multiple_of(cols) {
+            text.push('\n');
+        }
+        let position = self.prompt_width + width(&self.buf[..self.cursor]);
+        let (row, col) = (position / cols, position % cols);
+        if end / cols > row {
+            text.push_str(&format!("\x1b[{}A", end / cols - row));
+        }
+        text.push('\r');
+        if col > 0 {
+            text.push_str(&format!("\x1b[{}C", col));
+        }
+        self.row = row;
+
+        out.write_all(text.as_bytes())?;
+        out.flush()
+    }
+}
+
+/// Complete the word before the cursor, see [`complete_line`]. A single
+/// candidate replaces the word, several ones are shortened to their longest
+/// common prefix, or listed below the line when that adds nothing.
+fn complete_word(line: &mut Line, state: &mut ShellState, out: &mut dyn Write) -> io::Result<()> {
+    let (word, candidates) = complete_line(&line.buf, line.cursor, state);
+    if let [candidate] = candidates.as_slice() {
+        let space = if candidate.is_partial() { "" } else { " " };
+        line.replace(word.start, &(candidate.text.clone() + space));
+        return Ok(());
+    }
+    let Some(first) = candidates.first() else {
+        out.write_all(b"\x07")?;
+        return Ok(());
+    };
+
+    let mut common = first.text.as_str();
+    for candidate in &candidates[1..] {
+        let len = common
+            .char_indices()
+            .zip(candidate.text.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(common.len().min(candidate.text.len()), |((i, _), _)| i);
+        common = &common[..len];
+    }
+    let common_chars = common.chars().collect::<Vec<_>>();
+    if current_word(&common_chars, common_chars.len()).text.len() > word.text.len() {
+        line.replace(word.start, common);
+        return Ok(());
+    }
+
+    if candidates.len() > LIST_QUERY_ITEMS {
+        let cursor = line.cursor;
+        line.cursor = line.buf.len();
+        line.refresh(out)?;
+        line.cursor = cursor;
+        write!(
+            out,
+            "\nDisplay all {} possibilities? (y or n)",
+            candidates.len()
+        )?;
+        out.flush()?;
+        if !matches!(read_key()?, Key::Char('y' | 'Y')) {
+            writeln!(out)?;
+            return line.redraw(out);
+        }
+    }
+    let names = candidates
+        .into_iter()
+        .map(|candidate| candidate.display)
+        .collect::<Vec<_>>();
+    let cols = dimensions().map(|(w, _)| w).unwrap_or(80);
+    line.print_below(&format_grid(&names, cols), out)
+}
+
+/// Interactive line editor used by the REPL when stdin is a terminal.
+///
+/// Supports cursor motions (arrows, Home/End, Ctrl-A/E/B/F, Alt-B/F and
+/// Ctrl-arrows for words), deletion (Backspace, Delete, Ctrl-D), kills that
+/// can be yanked back with Ctrl-Y (Ctrl-K, Ctrl-U, Ctrl-W, Alt-D,
+/// Alt-Backspace), Ctrl-T to transpose, Ctrl-L to clear the screen,
+/// Up/Down (or Ctrl-P/N) to walk through the history, Ctrl-R/Ctrl-S to
+/// search it incrementally and Tab to complete the word before the cursor.
+#[derive(Debug, Default)]
+pub struct Editor {
+    killed: String, // Text removed by the last kill, inserted back by Ctrl-Y
+}
+
+impl Editor {
+    pub fn new() -> Self {
+        Editor::default()
+    }
+
+    /// Print `prompt` and read a line into `buf`, including its trailing
+    /// newline. When stdin or stdout is not a terminal, the line is read as
+    /// is without any editing.
+    ///
+    /// # Parameters
+    /// - `prompt`: prompt to print, ANSI colours included, with non-printing
+    ///   text between `\x01` and `\x02`; only its last line is redrawn while
+    ///   editing.
+    /// - `state`: shell state, for the history entries and completion.
+    /// - `buf`: string the line is appended to.
+    ///
+    /// # Returns
+    /// - number of bytes read, `0` at end of file, or an error of kind
+    ///   `Interrupted` when the line is abandoned with Ctrl-C.
+    pub fn read_line(
+        &mut self,
+        prompt: &str,
+        state: &mut ShellState,
+        buf: &mut String,
+    ) -> io::Result<usize> {
+        let mut stdout = io::stdout();
+        // The markers of non-printing text only matter to `display_width`
+        let shown = |text: &str| text.replace(['\x01', '\x02'], "");
+        if unsafe { libc::isatty(0) == 0 || libc::isatty(1) == 0 } {
+            write!(stdout, "{}", shown(prompt))?;
+            stdout.flush()?;
+            return read_plain_line(buf);
+        }
+
+        let (head, prompt) = match prompt.rsplit_once('\n') {
+            Some((head, last)) => (Some(shown(head)), last),
+            None => (None, prompt),
+        };
+        let raw = RawMode::enable()?;
+        let mut line = Line {
+            buf: Vec::new(),
+            cursor: 0,
+            head,
+            prompt: shown(prompt),
+            prompt_width: display_width(prompt),
+            row: 0,
+            highlight: None,
+        };
+        line.redraw(&mut stdout)?;
+        let result = self.edit(&mut line, state, &mut stdout);
+        drop(raw);
+
+        let text = result?;
+        let size = text.as_ref().map_or(0, |text| text.len());
+        if let Some(text) = text {
+            buf.push_str(&text);
+        }
+        Ok(size)
+    }
+
+    /// Handle key presses until the line is accepted.
+    ///
+    /// # Returns
+    /// - the line with its newline, `None` for Ctrl-D on an empty line.
+    fn edit(
+        &mut self,
+        line: &mut Line,
+        state: &mut ShellState,
+        out: &mut dyn Write,
+    ) -> io::Result<Option<String>> {
+        let hist = state.hist.entries().to_vec();
+        let mut index = hist.len(); // Entry shown, `hist.len()` for the new line
+        let mut draft = String::new(); // New line, kept while browsing the history
+        let mut pending = None; // Key ending a search, handled as if just typed
+        loop {
+            let key = match pending.take() {
+                Some(key) => key,
+                None => read_key()?,
+            };
+            match key {
+                Key::Enter => {
+                    line.cursor = line.buf.len();
+                    line.refresh(out)?;
+                    write!(out, "\r\n")?;
+                    return Ok(Some(line.buf.iter().collect::<String>() + "\n"));
+                }
+                Key::Ctrl('c') => {
+                    line.cursor = line.buf.len();
+                    line.refresh(out)?;
+                    write!(out, "^C\r\n")?;
+                    out.flush()?;
+                    return Err(io::Error::from(ErrorKind::Interrupted));
+                }
+                Key::Ctrl('d') | Key::Eof if line.buf.is_empty() => {
+                    write!(out, "\r\n")?;
+                    return Ok(None);
+                }
+                Key::Ctrl('d') | Key::Delete => {
+                    if line.cursor < line.buf.len() {
+                        line.buf.remove(line.cursor);
+                    }
+                }
+                Key::Eof => {}
+                Key::Backspace | Key::Ctrl('h') => {
+                    if line.cursor > 0 {
+                        line.cursor -= 1;
+                        line.buf.remove(line.cursor);
+                    }
+                }
+                Key::Char(ch) => line.insert(&[ch]),
+                Key::Tab => complete_word(line, state, out)?,
+                Key::Left | Key::Ctrl('b') => line.cursor = line.cursor.saturating_sub(1),
+                Key::Right | Key::Ctrl('f') => line.cursor = (line.cursor + 1).min(line.buf.len()),
+                Key::Home | Key::Ctrl('a') => line.cursor = 0,
+                Key::End | Key::Ctrl('e') => line.cursor = line.buf.len(),
+                Key::WordLeft | Key::Alt('b') => line.cursor = line.word_left(),
+                Key::WordRight | Key::Alt('f') => line.cursor = line.word_right(),
+                Key::Ctrl('k') => self.killed = line.remove(line.cursor, line.buf.len()),
+                Key::Ctrl('u') => self.killed = line.remove(0, line.cursor),
+                Key::Ctrl('w') => self.killed = line.remove(line.blank_word_left(), line.cursor),
+                Key::AltBackspace => self.killed = line.remove(line.word_left(), line.cursor),
+                Key::Alt('d') => {
+                    let (from, to) = (line.cursor, line.word_right());
+                    self.killed = line.remove(from, to);
+                }
+                Key::Ctrl('y') => line.insert(&self.killed.chars().collect::<Vec<_>>()),
+                Key::Ctrl('t') if line.cursor > 0 && line.buf.len() > 1 => {
+                    if line.cursor == line.buf.len() {
+                        line.cursor -= 1;
+                    }
+                    line.buf.swap(line.cursor - 1, line.cursor);
+                    line.cursor += 1;
+                }
+                Key::Ctrl('l') => {
+                    write!(out, "\x1b[H\x1b[2J")?;
+                    line.redraw(out)?;
+                }
+                Key::Up | Key::Ctrl('p') if index > 0 => {
+                    if index == hist.len() {
+                        draft = line.buf.iter().collect();
+                    }
+                    index -= 1;
+                    line.set(&hist[index]);
+                }
+                Key::Down | Key::Ctrl('n') if index < hist.len() => {
+                    index += 1;
+                    line.set(hist.get(index).unwrap_or(&draft));
+                }
+                Key::Ctrl(ch @ ('r' | 's')) => {
+                    if index == hist.len() {
+                        draft = line.buf.iter().collect();
+                    }
+                    if let SearchEnd::Accepted { key, entry } =
+                        search(line, &hist, index, ch == 'r', out)?
+                    {
+                        index = entry.unwrap_or(index);
+                        pending = Some(key);
+                    }
+                }
+                _ => continue,
+            }
+            line.refresh(out)?;
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use super::{Key, Line, display_width, read_key};
+use std::io;
+use std::io::Write;
+
+/// How an incremental search ended.
+pub(super) enum SearchEnd {
+    /// Esc or Ctrl-G: the line is back as it was before the search.
+    Aborted,
+    /// Any other key: the match stays in the line and the key is handled by
+    /// the editor as usual, so Enter runs the match right away.
+    Accepted {
+        key: Key,
+        entry: Option<usize>, // History index of the match, if any
+    },
+}
+
+/// Find the closest entry holding `query`, starting at index `from` and
+/// going towards older entries when `backward`. Entries equal to `skip` are
+/// passed over, so stepping does not stop on the same line twice.
+///
+/// # Returns
+/// - the index of the entry and the position of the match in it, counted
+///   in characters: the last occurrence going backward, the first going
+///   forward.
+fn find(
+    hist: &[String],
+    query: &str,
+    from: Option<usize>,
+    backward: bool,
+    skip: Option<&str>,
+) -> Option<(usize, usize)> {
+    let from = from.filter(|&from| from < hist.len())?;
+    let matches = |index: &usize| {
+        let entry = hist[*index].as_str();
+        Some(entry) != skip && entry.contains(query)
+    };
+    let index = if backward {
+        (0..=from).rev().find(matches)?
+    } else {
+        (from..hist.len()).find(matches)?
+    };
+    let entry = &hist[index];
+    let byte = if backward {
+        entry.rfind(query)?
+    } else {
+        entry.find(query)?
+    };
+    Some((index, entry[..byte].chars().count()))
+}
+
+/// Search the history incrementally, as started by Ctrl-R (`backward`) or
+/// Ctrl-S. Each typed character narrows the search, Backspace widens it
+/// again, and Ctrl-R/Ctrl-S step to the next older/newer match. The match
+/// is shown in the line, highlighted, below a `(reverse-i-search)` prompt.
+///
+/// # Parameters
+/// - `line`: line being edited, restored when the search is aborted.
+/// - `hist`: history entries, oldest first.
+/// - `start`: index of the entry shown when the search started,
+///   `hist.len()` for a new line.
+/// - `backward`: direction the search starts in.
+/// - `out`: terminal output.
+pub(super) fn search(
+    line: &mut Line,
+    hist: &[String],
+    start: usize,
+    mut backward: bool,
+    out: &mut dyn Write,
+) -> io::Result<SearchEnd> {
+    let saved = (line.buf.clone(), line.cursor);
+    let prompt = std::mem::take(&mut line.prompt);
+    let prompt_width = line.prompt_width;
+    let first = |backward: bool| {
+        if backward {
+            start.checked_sub(1)
+        } else {
+            Some(start)
+        }
+    };
+
+    let mut query = String::new();
+    let mut found: Option<(usize, usize)> = None; // Entry and position of the match
+    let mut failed = false;
+    let end = loop {
+        line.prompt = format!(
+            "({}{}i-search)`{}': ",
+            if failed { "failed " } else { "" },
+            if backward { "reverse-" } else { "" },
+            query
+        );
+        line.prompt_width = display_width(&line.prompt);
+        line.highlight = match found {
+            Some((_, position)) if !query.is_empty() => {
+                Some((position, position + query.chars().count()))
+            }
+            _ => None,
+        };
+        line.refresh(out)?;
+
+        match read_key()? {
+            key @ (Key::Ctrl('r') | Key::Ctrl('s')) => {
+                backward = key == Key::Ctrl('r');
+                let (from, skip) = match found {
+                    Some((index, _)) if backward => (index.checked_sub(1), Some(&hist[index])),
+                    Some((index, _)) => (Some(index + 1), Some(&hist[index])),
+                    None => (first(backward), None),
+                };
+                match find(hist, &query, from, backward, skip.map(String::as_str)) {
+                    Some(next) => {
+                        found = Some(next);
+                        failed = false;
+                    }
+                    None => failed = true,
+                }
+            }
+            Key::Char(ch) => {
+                query.push(ch);
+                let from = found.map_or(first(backward), |(index, _)| Some(index));
+                match find(hist, &query, from, backward, None) {
+                    Some(next) => found = Some(next),
+                    None => failed = true,
+                }
+            }
+            Key::Backspace | Key::Ctrl('h') => {
+                query.pop();
+                found = if query.is_empty() {
+                    None
+                } else {
+                    find(hist, &query, first(backward), backward, None)
+                };
+                failed = found.is_none() && !query.is_empty();
+                if found.is_none() {
+                    (line.buf, line.cursor) = saved.clone();
+                }
+            }
+            Key::Escape | Key::Ctrl('g') => {
+                (line.buf, line.cursor) = saved;
+                break SearchEnd::Aborted;
+            }
+            key => {
+                break SearchEnd::Accepted {
+                    key,
+                    entry: found.map(|(index, _)| index),
+                };
+            }
+        }
+        if let Some((index, position)) = found {
+            line.buf = hist[index].chars().collect();
+            line.cursor = position;
+        }
+    };
+
+    line.prompt = prompt;
+    line.prompt_width = prompt_width;
+    line.highlight = None;
+    line.refresh(out)?;
+    Ok(end)
+}
@@ -0,0 +1,119 @@
+use crate::{Builtin, Command, ShellState, Streams, spawn_command, split_assignment, write_error};
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// Print the environment, or run a command with additional variables.
+///
+/// # Parameters
+/// - `args`: options `-i` (start from an empty environment) and `-u NAME`
+///   (leave `NAME` out of it), then `NAME=value` words added to the
+///   environment, then the command to run and its arguments.
+/// - `state`: shell state holding the exported variables; the options only
+///   change the environment of `env`, not the variables of the shell.
+/// - `stdout`: stream the environment is written to.
+/// - `stderr`: stream errors are written to.
+///
+/// # Returns
+/// - exit status of the command, `0` after printing the environment, or
+///   `125` for an invalid option.
+pub fn env(
+    args: &[String],
+    state: &mut ShellState,
+    stdout: &mut dyn Write,
+    stderr: &mut dyn Write,
+) -> i32 {
+    let usage = |stderr: &mut dyn Write, message: &str| {
+        write_error(stderr, message);
+        write_error(
+            stderr,
+            "env: usage: env [-i] [-u name] [NAME=value ...] [command [arg ...]]",
+        );
+        125
+    };
+    let mut ignore_environment = false;
+    let mut unset = Vec::new();
+    let mut i = 0;
+    while let Some(arg) = args.get(i) {
+        match arg.as_str() {
+            "-i" | "-" => ignore_environment = true,
+            "-u" => {
+                let Some(name) = args.get(i + 1) else {
+                    return usage(stderr, "env: option requires an argument -- 'u'");
+                };
+                unset.push(name.as_str());
+                i += 1;
+            }
+            "--" => {
+                i += 1;
+                break;
+            }
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                return usage(stderr, &format!("env: invalid option -- '{}'", &arg[1..]));
+            }
+            _ => break,
+        }
+        i += 1;
+    }
+
+    let mut command = Command::default();
+    for arg in &args[i..] {
+        match split_assignment(arg) {
+            Some((name, value)) if command.name.is_empty() => {
+                command
+                    .assignments
+                    .push((name.to_string(), value.to_string()));
+            }
+            _ if command.name.is_empty() => command.name = arg.clone(),
+            _ => command.args.push(arg.clone()),
+        }
+    }
+
+    // Variables left out are only unexported while `env` runs, so `PATH`
+    // still finds the command as it does for `env -i cmd`
+    let vars = state.vars.clone();
+    for (name, var) in state.vars.iter_mut() {
+        if ignore_environment || unset.contains(&name.as_str()) {
+            var.exported = false;
+        }
+    }
+    let status = if command.name.is_empty() {
+        print_environment(&command, state, stdout)
+    } else {
+        let _ = stdout.flush();
+        spawn_command(&command, state)
+    };
+    state.vars = vars;
+    status
+}
+
+/// Print the exported variables and the assignments of `command`, sorted.
+fn print_environment(command: &Command, state: &ShellState, stdout: &mut dyn Write) -> i32 {
+    let mut env: BTreeMap<&str, &str> = state
+        .vars
+        .iter()
+        .filter(|(_, var)| var.exported)
+        .map(|(name, var)| (name.as_str(), var.value.as_str()))
+        .collect();
+    for (name, value) in &command.assignments {
+        env.insert(name, value);
+    }
+    for (name, value) in env {
+        if writeln!(stdout, "{}={}", name, value).is_err() {
+            return 1;
+        }
+    }
+    0
+}
+
+/// `env` builtin, see [`env`].
+pub struct EnvBuiltin;
+
+impl Builtin for EnvBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        env(args, state, io.stdout, io.stderr)
+    }
+
+    fn flags(&self) -> &str {
+        "iu"
+    }
+}
@@ -0,0 +1,165 @@
+use crate::*;
+
+/// Run a compound command in the current shell.
+///
+/// The list of a `Subshell` runs as is: the caller forks for it.
+///
+/// # Returns
+/// - exit status code (i32) of the last command run by the compound
+///   command, `0` when none ran.
+pub fn exec_compound(compound: &Compound, state: &mut ShellState) -> i32 {
+    match compound {
+        Compound::If {
+            branches,
+            otherwise,
+        } => exec_if(branches, otherwise.as_ref(), state),
+        Compound::While {
+            condition,
+            body,
+            until,
+        } => exec_while(condition, body, *until, state),
+        Compound::For { name, words, body } => exec_for(name, words.as_deref(), body, state),
+        Compound::Case { word, items } => exec_case(word, items, state),
+        Compound::Group(list) | Compound::Subshell(list) => exec_list(list, state),
+    }
+}
+
+/// Run the condition of a compound command, whose failure does not stop
+/// the shell under `set -e`.
+fn exec_condition(condition: &List, state: &mut ShellState) -> i32 {
+    let in_condition = std::mem::replace(&mut state.in_condition, true);
+    let status = exec_list(condition, state);
+    state.in_condition = in_condition;
+    status
+}
+
+/// Run the body of the first branch whose condition succeeds, else the
+/// `else` body.
+fn exec_if(branches: &[(List, List)], otherwise: Option<&List>, state: &mut ShellState) -> i32 {
+    for (condition, body) in branches {
+        let status = exec_condition(condition, state);
+        if state.flow.is_some() || interrupted() {
+            return status;
+        }
+        if status == 0 {
+            return exec_list(body, state);
+        }
+    }
+    otherwise.map_or(0, |body| exec_list(body, state))
+}
+
+/// Carry out a `break` or `continue` that reached a loop.
+///
+/// # Returns
+/// - `true` when the loop must stop, the jump being passed on to the
+///   enclosing loop when it goes further, or to the function for `return`;
+///   every loop stops after `Ctrl+C`.
+fn leave_loop(state: &mut ShellState) -> bool {
+    if interrupted() {
+        return true;
+    }
+    match state.flow {
+        None => false,
+        Some(Flow::Return) => true,
+        Some(Flow::Break(count)) => {
+            state.flow = (count > 1).then(|| Flow::Break(count - 1));
+            true
+        }
+        Some(Flow::Continue(count)) if count > 1 => {
+            state.flow = Some(Flow::Continue(count - 1));
+            true
+        }
+        Some(Flow::Continue(_)) => {
+            state.flow = None;
+            false
+        }
+    }
+}
+
+/// Run `body` as long as `condition` succeeds, or fails for `until`.
+fn exec_while(condition: &List, body: &List, until: bool, state: &mut ShellState) -> i32 {
+    let mut status = 0;
+    state.loop_depth += 1;
+    loop {
+        let success = exec_condition(condition, state) == 0;
+        if state.flow.is_some() || interrupted() {
+            if leave_loop(state) {
+                break;
+            }
+            continue;
+        }
+        if success == until {
+            break;
+        }
+        status = exec_list(body, state);
+        if leave_loop(state) {
+            break;
+        }
+    }
+    state.loop_depth -= 1;
+    status
+}
+
+/// Run `body` once for every field the raw `words` expand to, or for every
+/// positional parameter, with the variable `name` set to it.
+fn exec_for(name: &str, words: Option<&[String]>, body: &List, state: &mut ShellState) -> i32 {
+    let values = match words {
+        Some(words) => {
+            let mut values = Vec::new();
+            for word in words {
+                match expand_word(word, state) {
+                    Ok(fields) => values.extend(fields),
+                    Err(err) => {
+                        print_error(&err);
+                        return 1;
+                    }
+                }
+            }
+            values
+        }
+        None => state.positional.clone(),
+    };
+
+    let mut status = 0;
+    state.loop_depth += 1;
+    for value in values {
+        state.set_var(name, &value);
+        status = exec_list(body, state);
+        if leave_loop(state) {
+            break;
+        }
+    }
+    state.loop_depth -= 1;
+    status
+}
+
+/// Run the body of the first item with a pattern matching `word`. The
+/// patterns are expanded one at a time, until one matches.
+fn exec_case(word: &str, items: &[CaseItem], state: &mut ShellState) -> i32 {
+    let word = match expand_string(word, state) {
+        Ok(word) => word,
+        Err(err) => {
+            print_error(&err);
+            return 1;
+        }
+    };
+    for item in items {
+        for pattern in &item.patterns {
+            let pattern = match expand_pattern(pattern, state) {
+                Ok(pattern) => pattern,
+                Err(err) => {
+                    print_error(&err);
+                    return 1;
+                }
+            };
+            if pattern_matches(&pattern, &word) {
+                return if item.body.is_empty() {
+                    0
+                } else {
+                    exec_list(&item.body, state)
+                };
+            }
+        }
+    }
+    0
+}
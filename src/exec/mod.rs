@@ -0,0 +1,764 @@
+use crate::*;
+use std::ffi::CStr;
+use std::ffi::CString;
+use std::fs;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::ErrorKind;
+use std::io::Read;
+use std::io::Write;
+use std::io::stdout;
+use std::os::fd::AsRawFd;
+use std::os::fd::FromRawFd;
+use std::os::fd::IntoRawFd;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+pub use compound::*;
+pub mod compound;
+
+/// Execute a function or a builtin command by name, looking it up in the
+/// functions, then in the registry of the shell state, and handing a
+/// builtin the process standard streams.
+///
+/// # Parameters
+/// - `command`: command to run, its `name` selects the function or builtin.
+/// - `state`: shell state the command may read or change.
+///
+/// # Returns
+/// - exit status code (i32) of the executed command.
+pub fn exec_command(command: &Command, state: &mut ShellState) -> i32 {
+    if let Some(function) = state.functions.get(&command.name).cloned() {
+        return call_function(&function, &command.args, state);
+    }
+    let Some(builtin) = state.builtins.get(&command.name) else {
+        print_error(&format!("Command <{}\x1b[31m> not found", command.name));
+        return 127;
+    };
+    let mut stdin = io::stdin();
+    let mut stdout = io::stdout();
+    let mut stderr = io::stderr();
+    let mut streams = Streams {
+        stdin: &mut stdin,
+        stdout: &mut stdout,
+        stderr: &mut stderr,
+        terminal: unsafe { libc::isatty(libc::STDOUT_FILENO) } == 1,
+    };
+    let status = builtin.run(&command.args, state, &mut streams);
+    let _ = stdout.flush();
+    status
+}
+
+/// Execute a list of commands, one and-or list after the other.
+///
+/// Within an and-or list, a pipeline after `&&` only runs when the previous
+/// one succeeded and a pipeline after `||` only when it failed, skipped
+/// pipelines leaving the status unchanged. `last_command_staus` is updated
+/// after every pipeline so `$?` is right for the next one. With `set -e`,
+/// the shell exits when the last pipeline of an and-or list fails, unless
+/// it starts with `!` or runs as a condition. A pending `break` or
+/// `continue` stops the list. The traps of the signals that arrived run
+/// between two pipelines. An and-or list ended with `&` runs as a
+/// background job, see [`spawn_background`].
+///
+/// # Parameters
+/// - `list`: parsed commands to run.
+/// - `state`: shell state handed to the builtins.
+///
+/// # Returns
+/// - exit status code (i32) of the last pipeline that ran.
+pub fn exec_list(list: &List, state: &mut ShellState) -> i32 {
+    for and_or in &list.items {
+        if and_or.background {
+            state.last_command_staus = spawn_background(and_or, state);
+            continue;
+        }
+        let rest = and_or
+            .rest
+            .iter()
+            .map(|(connector, p)| (Some(*connector), p));
+        let pipelines = std::iter::once((None, &and_or.first)).chain(rest);
+        for (i, (connector, pipeline)) in pipelines.enumerate() {
+            let success = state.last_command_staus == 0;
+            if connector.is_some_and(|connector| success != (connector == Connector::And)) {
+                continue;
+            }
+            // Only a failure of the last pipeline of an and-or list stops `set -e`
+            let checked = i == and_or.rest.len() && !pipeline.negated;
+            let in_condition = state.in_condition;
+            state.in_condition |= !checked;
+            state.last_command_staus = exec_pipeline(pipeline, state);
+            state.in_condition = in_condition;
+            if state.flow.is_some() {
+                return state.last_command_staus;
+            }
+            run_traps(state);
+            // `Ctrl+C` abandons the rest of the commands
+            if interrupted() {
+                state.last_command_staus = 130;
+                return 130;
+            }
+            let failed = state.last_command_staus != 0;
+            if failed && checked && !state.in_condition && state.options.errexit {
+                exit_shell(state.last_command_staus, state);
+            }
+        }
+    }
+    state.last_command_staus
+}
+
+/// Run an and-or list in a forked copy of the shell without waiting for it,
+/// as a job in a process group of its own. Without job control its stdin
+/// is `/dev/null`, so it cannot take the input of the shell.
+///
+/// # Returns
+/// - `0`, or `1` when the shell could not fork.
+fn spawn_background(and_or: &AndOr, state: &mut ShellState) -> i32 {
+    let _ = stdout().flush();
+    let and_or = AndOr {
+        background: false,
+        ..and_or.clone()
+    };
+    match unsafe { libc::fork() } {
+        -1 => {
+            print_error(&format!("fork: {}", io::Error::last_os_error()));
+            1
+        }
+        0 => {
+            unsafe { libc::setpgid(0, 0) };
+            if !state.job_control
+                && let Ok(null) = File::open("/dev/null")
+            {
+                unsafe { libc::dup2(null.as_raw_fd(), 0) };
+            }
+            init_child(state);
+            let list = List {
+                items: vec![and_or],
+            };
+            let status = exec_list(&list, state);
+            let _ = stdout().flush();
+            unsafe { libc::_exit(status) }
+        }
+        pid => {
+            unsafe { libc::setpgid(pid, pid) };
+            state.last_background_pid = Some(pid);
+            let id = add_job(Job::new(pid, vec![pid], and_or.to_string()), state);
+            if state.job_control {
+                eprintln!("[{}] {}", id, pid);
+            }
+            0
+        }
+    }
+}
+
+/// Execute a pipeline and wait for all of its stages.
+///
+/// A lone builtin or function runs inside the shell process so it can
+/// change the shell state (e.g. `cd`), and so do assignments without a
+/// command such as `a=1`, function definitions and lone compound commands
+/// other than `( ... )`. Otherwise every
+/// stage is forked, with its stdin and stdout connected to the
+/// neighbouring stages through pipes. Words are expanded right before each
+/// command runs.
+///
+/// # Parameters
+/// - `pipeline`: parsed commands to run.
+/// - `state`: shell state handed to the builtins.
+///
+/// # Returns
+/// - exit status code (i32) of the last stage, inverted for a pipeline
+///   starting with `!`.
+pub fn exec_pipeline(pipeline: &Pipeline, state: &mut ShellState) -> i32 {
+    let status = exec_stages(&pipeline.commands, state);
+    if pipeline.negated {
+        (status == 0) as i32
+    } else {
+        status
+    }
+}
+
+/// Execute the stages of a pipeline, see [`exec_pipeline`].
+fn exec_stages(commands: &[Stage], state: &mut ShellState) -> i32 {
+    let command = match commands {
+        [Stage::Simple(command)] => command,
+        [Stage::Compound(compound, redirects)] => {
+            return exec_redirected(compound, redirects, state);
+        }
+        [Stage::Function(function)] => {
+            let name = function.name.clone();
+            state.functions.insert(name, Rc::new(function.clone()));
+            return 0;
+        }
+        _ => return spawn_stages(commands, false, state),
+    };
+    if let Some(status) = exec_alias(command, state) {
+        return status;
+    }
+
+    let command = match expand_command(command, state) {
+        Ok(command) => command,
+        Err(err) => {
+            print_error(&err);
+            return 1;
+        }
+    };
+    trace(&command, state);
+    if !command.name.is_empty() && !state.is_internal(&command.name) {
+        return spawn_stages(&[Stage::Simple(command)], true, state);
+    }
+
+    let saved = match apply_redirects(&command.redirects, &state.current_dir, true) {
+        Ok(saved) => saved,
+        Err(err) => {
+            print_error(&err);
+            return 1;
+        }
+    };
+    let status = if command.name.is_empty() {
+        for (name, value) in &command.assignments {
+            state.set_var(name, value);
+        }
+        // `a=$(cmd)` reports the status of `cmd`
+        state.substitution_status.unwrap_or(0)
+    } else {
+        // Assignments before a builtin only last for that command
+        let previous: Vec<_> = command
+            .assignments
+            .iter()
+            .map(|(name, _)| (name.clone(), state.vars.get(name).cloned()))
+            .collect();
+        for (name, value) in &command.assignments {
+            state.export_var(name, Some(value));
+        }
+        let status = exec_command(&command, state);
+        for (name, var) in previous.into_iter().rev() {
+            match var {
+                Some(var) => state.vars.insert(name, var),
+                None => state.vars.remove(&name),
+            };
+        }
+        status
+    };
+    let _ = stdout().flush();
+    restore_fds(saved);
+    status
+}
+
+/// Run a compound command inside the shell process, with its redirections
+/// applied around it, except for `( ... )` which runs in a forked copy of
+/// the shell.
+///
+/// # Returns
+/// - exit status code (i32) of the compound command.
+pub fn exec_redirected(compound: &Compound, redirects: &[Redirect], state: &mut ShellState) -> i32 {
+    if let Compound::Subshell(_) = compound {
+        let stage = Stage::Compound(compound.clone(), redirects.to_vec());
+        return spawn_stages(&[stage], false, state);
+    }
+    let saved = match expand_redirects(redirects, state)
+        .and_then(|redirects| apply_redirects(&redirects, &state.current_dir, true))
+    {
+        Ok(saved) => saved,
+        Err(err) => {
+            print_error(&err);
+            return 1;
+        }
+    };
+    let status = exec_compound(compound, state);
+    let _ = stdout().flush();
+    restore_fds(saved);
+    status
+}
+
+/// Prepare a forked child to run commands: the signals the shell catches or
+/// ignores get their default behaviour back, since Rust ignores `SIGPIPE`
+/// and the shell swallows `Ctrl+C` and the stop signals of the terminal.
+/// Jobs belong to the shell that started them, and the commands run by the
+/// child stay in its process group. Traps are not inherited, but ignored
+/// signals stay ignored.
+fn init_child(state: &mut ShellState) {
+    unsafe {
+        for signal in [
+            libc::SIGINT,
+            libc::SIGPIPE,
+            libc::SIGTSTP,
+            libc::SIGTTIN,
+            libc::SIGTTOU,
+        ] {
+            libc::signal(signal, libc::SIG_DFL);
+        }
+    }
+    state.jobs.clear();
+    state.job_control = false;
+    reset_traps(state);
+}
+
+/// Fork and run a command whose words are already expanded, then wait for
+/// it. Its assignments are exported to the program it runs, as done by
+/// `env NAME=value cmd`.
+///
+/// # Returns
+/// - exit status code (i32) of the command.
+pub fn spawn_command(command: &Command, state: &mut ShellState) -> i32 {
+    spawn_stages(&[Stage::Simple(command.clone())], true, state)
+}
+
+/// Run a list in a forked copy of the shell and capture what it writes to
+/// stdout, as done for `$(...)`.
+///
+/// # Parameters
+/// - `list`: parsed commands to run.
+/// - `state`: shell state copied into the child; changes made by the list,
+///   such as `cd`, do not affect the caller.
+///
+/// # Returns
+/// - the captured output and the exit status of the list, or an error
+///   message when the child could not be started.
+pub fn capture_output(list: &List, state: &mut ShellState) -> Result<(String, i32), String> {
+    // Anything still buffered would otherwise be written by the child
+    let _ = stdout().flush();
+
+    let mut fds = [-1; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } == -1 {
+        return Err(format!("pipe: {}", io::Error::last_os_error()));
+    }
+    match unsafe { libc::fork() } {
+        -1 => {
+            let err = io::Error::last_os_error();
+            unsafe {
+                libc::close(fds[0]);
+                libc::close(fds[1]);
+            }
+            Err(format!("fork: {}", err))
+        }
+        0 => {
+            init_child(state);
+            unsafe {
+                libc::close(fds[0]);
+                libc::dup2(fds[1], 1);
+                libc::close(fds[1]);
+            }
+            let status = exec_list(list, state);
+            let _ = stdout().flush();
+            unsafe { libc::_exit(status) }
+        }
+        pid => {
+            unsafe { libc::close(fds[1]) };
+            let mut output = Vec::new();
+            let _ = unsafe { File::from_raw_fd(fds[0]) }.read_to_end(&mut output);
+            let status = wait_child(pid);
+            Ok((String::from_utf8_lossy(&output).to_string(), status))
+        }
+    }
+}
+
+/// Fork every stage of a pipeline and wait for all of them.
+///
+/// # Parameters
+/// - `commands`: stages of the pipeline.
+/// - `expanded`: the words of the simple commands are already expanded,
+///   otherwise each child expands its own command.
+/// - `state`: shell state copied into the children.
+///
+/// # Returns
+/// - exit status code (i32) of the last stage.
+fn spawn_stages(commands: &[Stage], expanded: bool, state: &mut ShellState) -> i32 {
+    // Anything still buffered would otherwise be written by every child
+    let _ = stdout().flush();
+
+    // With job control, the stages form a process group given the terminal
+    let job_control = state.job_control;
+    let mut pgid = 0;
+    let mut pids = Vec::new();
+    let mut prev_read: Option<i32> = None;
+    let mut spawn_failed = false;
+    for (i, command) in commands.iter().enumerate() {
+        let is_last = i == commands.len() - 1;
+        let mut fds = [-1; 2];
+        if !is_last && unsafe { libc::pipe(fds.as_mut_ptr()) } == -1 {
+            print_error(&format!("pipe: {}", io::Error::last_os_error()));
+            spawn_failed = true;
+            break;
+        }
+
+        match unsafe { libc::fork() } {
+            -1 => {
+                print_error(&format!("fork: {}", io::Error::last_os_error()));
+                if !is_last {
+                    unsafe {
+                        libc::close(fds[0]);
+                        libc::close(fds[1]);
+                    }
+                }
+                spawn_failed = true;
+                break;
+            }
+            0 => {
+                if job_control {
+                    unsafe {
+                        libc::setpgid(0, pgid);
+                        libc::tcsetpgrp(libc::STDIN_FILENO, libc::getpgrp());
+                    }
+                }
+                init_child(state);
+                unsafe {
+                    if let Some(fd) = prev_read {
+                        libc::dup2(fd, 0);
+                        libc::close(fd);
+                    }
+                    if !is_last {
+                        libc::close(fds[0]);
+                        libc::dup2(fds[1], 1);
+                        libc::close(fds[1]);
+                    }
+                }
+                let status = run_child(command, expanded, state);
+                let _ = stdout().flush();
+                unsafe { libc::_exit(status) }
+            }
+            pid => {
+                if job_control {
+                    if pgid == 0 {
+                        pgid = pid;
+                    }
+                    // Also done by the child: whichever runs first wins
+                    unsafe {
+                        libc::setpgid(pid, pgid);
+                        libc::tcsetpgrp(libc::STDIN_FILENO, pgid);
+                    }
+                }
+                pids.push(pid);
+                if let Some(fd) = prev_read.take() {
+                    unsafe { libc::close(fd) };
+                }
+                if !is_last {
+                    unsafe { libc::close(fds[1]) };
+                    prev_read = Some(fds[0]);
+                }
+            }
+        }
+    }
+    if let Some(fd) = prev_read {
+        unsafe { libc::close(fd) };
+    }
+
+    let mut status = 0;
+    if job_control && !pids.is_empty() {
+        let pipeline = Pipeline {
+            commands: commands.to_vec(),
+            negated: false,
+        };
+        status = wait_foreground(Job::new(pgid, pids, pipeline.to_string()), state);
+    } else {
+        for pid in pids {
+            status = wait_child(pid);
+        }
+    }
+    if spawn_failed { 1 } else { status }
+}
+
+/// Body of a forked stage: expand the command if needed, apply its
+/// redirections and assignments, then run the builtin, the program or the
+/// compound command.
+///
+/// # Returns
+/// - exit status code the child must exit with.
+fn run_child(stage: &Stage, expanded: bool, state: &mut ShellState) -> i32 {
+    let command = match stage {
+        Stage::Simple(command) => command,
+        Stage::Compound(compound, redirects) => {
+            let applied = expand_redirects(redirects, state)
+                .and_then(|redirects| apply_redirects(&redirects, &state.current_dir, false));
+            if let Err(err) = applied {
+                print_error(&err);
+                return 1;
+            }
+            return exec_compound(compound, state);
+        }
+        Stage::Function(function) => {
+            let name = function.name.clone();
+            state.functions.insert(name, Rc::new(function.clone()));
+            return 0;
+        }
+    };
+    let expanded_command;
+    let command = if expanded {
+        command
+    } else if let Some(status) = exec_alias(command, state) {
+        return status;
+    } else {
+        match expand_command(command, state) {
+            Ok(command) => {
+                trace(&command, state);
+                expanded_command = command;
+                &expanded_command
+            }
+            Err(err) => {
+                print_error(&err);
+                return 1;
+            }
+        }
+    };
+    if let Err(err) = apply_redirects(&command.redirects, &state.current_dir, false) {
+        print_error(&err);
+        return 1;
+    }
+    for (name, value) in &command.assignments {
+        state.export_var(name, Some(value));
+    }
+    if command.name.is_empty() {
+        0
+    } else if state.is_internal(&command.name) {
+        exec_command(command, state)
+    } else {
+        exec_external(command, state)
+    }
+}
+
+/// Print an expanded command to stderr before it runs when `set -x` is on,
+/// after the `$PS4` prefix, `+ ` by default.
+fn trace(command: &Command, state: &ShellState) {
+    if !state.options.xtrace {
+        return;
+    }
+    let words = command
+        .assignments
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, quote(value)))
+        .chain(
+            std::iter::once(&command.name)
+                .filter(|name| !name.is_empty())
+                .chain(&command.args)
+                .map(|word| quote(word)),
+        )
+        .collect::<Vec<_>>();
+    if !words.is_empty() {
+        let prefix = state.get_var("PS4").unwrap_or("+ ");
+        eprintln!("{}{}", prefix, words.join(" "));
+    }
+}
+
+/// Rebind file descriptors as described by `redirects`, from left to right.
+///
+/// # Parameters
+/// - `redirects`: redirections of the command.
+/// - `current_dir`: base directory for relative file names.
+/// - `save`: keep a copy of every replaced descriptor so `restore_fds` can
+///   undo the redirections, needed when running inside the shell process.
+///
+/// # Returns
+/// - the saved `(fd, copy)` pairs, or an error message. Redirections already
+///   applied are undone on error.
+fn apply_redirects(
+    redirects: &[Redirect],
+    current_dir: &Path,
+    save: bool,
+) -> Result<Vec<(i32, i32)>, String> {
+    let mut saved = Vec::new();
+    for redirect in redirects {
+        if save && !saved.iter().any(|(fd, _)| *fd == redirect.fd) {
+            let copy = unsafe { libc::fcntl(redirect.fd, libc::F_DUPFD_CLOEXEC, 10) };
+            saved.push((redirect.fd, copy));
+        }
+        if let Err(err) = apply_redirect(redirect, current_dir) {
+            restore_fds(saved);
+            return Err(err);
+        }
+    }
+    Ok(saved)
+}
+
+/// Open the target of a single redirection and move it onto its descriptor.
+fn apply_redirect(redirect: &Redirect, current_dir: &Path) -> Result<(), String> {
+    let source = match redirect.kind {
+        RedirectKind::Duplicate => match redirect.target.parse::<i32>() {
+            Ok(fd) => fd,
+            Err(_) => return Err(format!("{}: bad file descriptor", redirect.target)),
+        },
+        kind => {
+            let mut options = OpenOptions::new();
+            match kind {
+                RedirectKind::Input => options.read(true),
+                RedirectKind::Append => options.append(true).create(true),
+                _ => options.write(true).create(true).truncate(true),
+            };
+            match options.open(current_dir.join(&redirect.target)) {
+                Ok(file) => file.into_raw_fd(),
+                Err(err) => return Err(format!("{}: {}", redirect.target, err)),
+            }
+        }
+    };
+    if source == redirect.fd {
+        return Ok(());
+    }
+    let res = unsafe { libc::dup2(source, redirect.fd) };
+    let err = io::Error::last_os_error();
+    if redirect.kind != RedirectKind::Duplicate {
+        unsafe { libc::close(source) };
+    }
+    if res == -1 {
+        return Err(format!("{}: {}", redirect.target, err));
+    }
+    Ok(())
+}
+
+/// Undo `apply_redirects`, putting the saved descriptors back in place.
+fn restore_fds(saved: Vec<(i32, i32)>) {
+    for (fd, copy) in saved.into_iter().rev() {
+        unsafe {
+            if copy == -1 {
+                libc::close(fd);
+            } else {
+                libc::dup2(copy, fd);
+                libc::close(copy);
+            }
+        }
+    }
+}
+
+/// Locate the program to run for `name`. Names containing a `/` are taken
+/// as a path relative to `current_dir`, other names are searched in the
+/// directories listed in the `PATH` variable of the shell.
+///
+/// # Returns
+/// - the program path, or the error message with the status to exit with:
+///   `126` when the file exists but cannot be executed, `127` when it is missing.
+pub fn find_program(name: &str, state: &ShellState) -> Result<PathBuf, (String, i32)> {
+    let current_dir = &state.current_dir;
+    if name.contains('/') {
+        let path = current_dir.join(name);
+        return match fs::metadata(&path) {
+            Err(err) => Err((format!("{}: {}", name, err), 127)),
+            Ok(meta) if meta.is_dir() => Err((format!("{}: Is a directory", name), 126)),
+            Ok(_) if !is_executable(&path) => Err((format!("{}: Permission denied", name), 126)),
+            Ok(_) => Ok(path),
+        };
+    }
+
+    let paths = state.get_var("PATH").unwrap_or("/usr/local/bin:/usr/bin:/bin");
+    let mut denied = false;
+    for dir in paths.split(':') {
+        // An empty entry stands for the current directory
+        let path = if dir.is_empty() {
+            current_dir.join(name)
+        } else {
+            current_dir.join(dir).join(name)
+        };
+        if path.is_file() {
+            if is_executable(&path) {
+                return Ok(path);
+            }
+            denied = true;
+        }
+    }
+    if denied {
+        Err((format!("{}: Permission denied", name), 126))
+    } else {
+        Err((format!("Command <{}\x1b[31m> not found", name), 127))
+    }
+}
+
+/// Replace the current (forked) process with an external program.
+///
+/// # Parameters
+/// - `command`: program name or path, and its arguments.
+/// - `state`: shell state providing the working directory and the
+///   exported variables that make up the environment of the program.
+///
+/// A file the kernel cannot execute, like a script without `#!` line, is
+/// run as a shell script by the forked shell, as other shells do.
+///
+/// # Returns
+/// - `127` when the program could not be found, `126` when it was found
+///   but could not be executed, or the status of the script.
+fn exec_external(command: &Command, state: &mut ShellState) -> i32 {
+    let path = match find_program(&command.name, state) {
+        Ok(path) => path,
+        Err((err, status)) => {
+            print_error(&err);
+            return status;
+        }
+    };
+    let argv = match std::iter::once(&command.name)
+        .chain(command.args.iter())
+        .map(|arg| CString::new(arg.as_str()))
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(argv) => argv,
+        Err(_) => {
+            print_error(&format!("{}: argument contains a nul byte", command.name));
+            return 126;
+        }
+    };
+    let Ok(envp) = state
+        .environ()
+        .into_iter()
+        .map(CString::new)
+        .collect::<Result<Vec<_>, _>>()
+    else {
+        print_error(&format!("{}: environment contains a nul byte", command.name));
+        return 126;
+    };
+    let Ok(program) = CString::new(path.as_os_str().as_bytes()) else {
+        print_error(&format!("{}: path contains a nul byte", command.name));
+        return 126;
+    };
+    let mut ptrs: Vec<*const libc::c_char> = argv.iter().map(|arg| arg.as_ptr()).collect();
+    ptrs.push(std::ptr::null());
+    let mut env_ptrs: Vec<*const libc::c_char> = envp.iter().map(|var| var.as_ptr()).collect();
+    env_ptrs.push(std::ptr::null());
+
+    unsafe { libc::execve(program.as_ptr(), ptrs.as_ptr(), env_ptrs.as_ptr()) };
+    let err = io::Error::last_os_error();
+    if err.raw_os_error() == Some(libc::ENOEXEC)
+        && let Ok(source) = fs::read_to_string(&path)
+    {
+        state.arg0 = command.name.clone();
+        state.positional = command.args.clone();
+        return run_script(&source, &command.name, state);
+    }
+    print_error(&format!("{}: {}", command.name, err));
+    if err.kind() == ErrorKind::NotFound { 127 } else { 126 }
+}
+
+/// Wait for a child process and convert its wait status to an exit code,
+/// see [`report_status`].
+fn wait_child(pid: libc::pid_t) -> i32 {
+    let mut status = 0;
+    while unsafe { libc::waitpid(pid, &mut status, 0) } == -1 {
+        if io::Error::last_os_error().kind() != ErrorKind::Interrupted {
+            return 1;
+        }
+    }
+    report_status(status)
+}
+
+/// Convert the wait status of a foreground child to an exit code.
+///
+/// Like other shells, a child killed by a signal other than `SIGINT` or
+/// `SIGPIPE` is reported on stderr (e.g. `Segmentation fault (core dumped)`).
+/// A child killed by `SIGINT` interrupts the shell as well, see
+/// [`interrupt`].
+///
+/// # Returns
+/// - the exit code of the child, or `128 + signal` when it was killed.
+pub fn report_status(status: libc::c_int) -> i32 {
+    if libc::WIFEXITED(status) {
+        libc::WEXITSTATUS(status)
+    } else if libc::WIFSIGNALED(status) {
+        let signal = libc::WTERMSIG(status);
+        if signal == libc::SIGINT {
+            interrupt();
+        } else if signal != libc::SIGPIPE {
+            let description = unsafe { CStr::from_ptr(libc::strsignal(signal)) };
+            let core = if libc::WCOREDUMP(status) { " (core dumped)" } else { "" };
+            print_error(&format!("{}{}", description.to_string_lossy(), core));
+        }
+        128 + signal
+    } else {
+        1
+    }
+}
@@ -0,0 +1,35 @@
+use crate::{Builtin, ShellState, Streams, exit_shell, write_error};
+use std::io::Write;
+
+/// Exit the shell, running the trap on `EXIT` first, see [`exit_shell`].
+///
+/// # Parameters
+/// - `args`: optional exit code.
+/// - `state`: shell state, whose `last_command_staus` is the code used when
+///   no argument is given.
+/// - `stderr`: stream errors are written to.
+///
+/// # Returns
+/// - `2` when the exit code is not a number, otherwise never returns.
+pub fn exit(args: &[String], state: &mut ShellState, stderr: &mut dyn Write) -> i32 {
+    if args.is_empty() {
+        exit_shell(state.last_command_staus, state);
+    } else {
+        match args[0].parse::<i32>() {
+            Ok(code) => exit_shell(code, state),
+            Err(_) => {
+                write_error(stderr, &format!("exit: Illegal number: {}", args[0]));
+                2
+            }
+        }
+    }
+}
+
+/// `exit` builtin, see [`exit`].
+pub struct ExitBuiltin;
+
+impl Builtin for ExitBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        exit(args, state, io.stderr)
+    }
+}
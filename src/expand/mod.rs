@@ -0,0 +1,544 @@
+use crate::*;
+use users::get_user_by_name;
+use users::os::unix::UserExt;
+
+/// A field being built from a raw word.
+#[derive(Debug, Default)]
+struct Field {
+    text: String,
+    pattern: String, // `text` with its quoted characters escaped, for globbing
+    quoted: bool,    // Part of the word was quoted, so an empty field is kept
+}
+
+/// Turns one raw word into fields: removes quotes, expands `~`, `$`
+/// parameters and command substitutions, splits unquoted expansion results on `$IFS`, then expands
+/// the fields holding unquoted pattern characters into file names.
+struct Expander<'a> {
+    state: &'a mut ShellState,
+    split: bool, // Field splitting and pathname expansion are done
+    fields: Vec<Field>,
+    current: Field,
+}
+
+impl<'a> Expander<'a> {
+    fn new(state: &'a mut ShellState, split: bool) -> Self {
+        Self {
+            state,
+            split,
+            fields: Vec::new(),
+            current: Field::default(),
+        }
+    }
+
+    /// Append quoted text, never split nor used as a pattern.
+    fn push_literal(&mut self, text: &str) {
+        for ch in text.chars() {
+            if "*?[]\\".contains(ch) {
+                self.current.pattern.push('\\');
+            }
+            self.current.pattern.push(ch);
+        }
+        self.current.text.push_str(text);
+    }
+
+    /// Append an unquoted character of the word itself, which may be part
+    /// of a pattern.
+    fn push_unquoted(&mut self, ch: char) {
+        self.current.text.push(ch);
+        self.current.pattern.push(ch);
+    }
+
+    /// Append the unquoted result of an expansion, splitting it on `$IFS`
+    /// when field splitting is enabled.
+    fn push_expansion(&mut self, text: &str) {
+        if !self.split {
+            self.push_literal(text);
+            return;
+        }
+        let ifs = self.state.get_var("IFS").unwrap_or(" \t\n").to_string();
+        for ch in text.chars() {
+            if !ifs.contains(ch) {
+                self.push_unquoted(ch);
+            } else if !ch.is_whitespace() || !self.current.text.is_empty() || self.current.quoted {
+                // A non-blank separator always ends a field, even an empty one
+                self.end_field(true);
+            }
+        }
+    }
+
+    /// Close the current field and start a new one.
+    fn end_field(&mut self, keep_empty: bool) {
+        let field = std::mem::take(&mut self.current);
+        if keep_empty || !field.text.is_empty() || field.quoted {
+            self.fields.push(field);
+        }
+    }
+
+    /// Expand the whole raw `word`.
+    fn expand(self, word: &str) -> Result<Vec<String>, String> {
+        let split = self.split;
+        let (fields, state) = self.fields(word)?;
+        if !split {
+            return Ok(fields.into_iter().map(|field| field.text).collect());
+        }
+
+        let options = &state.options;
+        let mut words = Vec::new();
+        for field in fields {
+            if !has_glob(&field.pattern) {
+                words.push(field.text);
+                continue;
+            }
+            let paths = glob(&field.pattern, &state.current_dir, options.globstar);
+            if !paths.is_empty() {
+                words.extend(paths);
+            } else if options.failglob {
+                return Err(format!("no match: {}", field.text));
+            } else if !options.nullglob {
+                words.push(field.text);
+            }
+        }
+        Ok(words)
+    }
+
+    /// Build the fields of the raw `word`, before pathname expansion.
+    ///
+    /// # Returns
+    /// - the fields, and the shell state given back for the pathname
+    ///   expansion.
+    fn fields(mut self, word: &str) -> Result<(Vec<Field>, &'a mut ShellState), String> {
+        let chars = word.chars().collect::<Vec<_>>();
+        let mut i = self.tilde(&chars);
+        let mut in_double = false;
+        // Quoting state before the current double quotes, restored when they
+        // only held an empty "$@"
+        let mut quoted_before = false;
+        let mut empty_at = false;
+        let mut other = false;
+
+        while i < chars.len() {
+            let ch = chars[i];
+            if in_double {
+                match ch {
+                    '"' => {
+                        in_double = false;
+                        if empty_at && !other {
+                            self.current.quoted = quoted_before;
+                        }
+                    }
+                    '\\' => {
+                        match chars.get(i + 1) {
+                            Some(&next) if ['$', '`', '"', '\\'].contains(&next) => {
+                                self.push_literal(&next.to_string());
+                                i += 1;
+                            }
+                            _ => self.push_literal("\\"),
+                        }
+                        other = true;
+                    }
+                    '$' => {
+                        let (next, is_empty_at) = self.dollar(&chars, i, true)?;
+                        empty_at |= is_empty_at;
+                        other |= !is_empty_at;
+                        i = next;
+                        continue;
+                    }
+                    '`' => {
+                        i = self.backquote(&chars, i, true)?;
+                        other = true;
+                    }
+                    _ => {
+                        self.push_literal(&ch.to_string());
+                        other = true;
+                    }
+                }
+                i += 1;
+                continue;
+            }
+
+            match ch {
+                '\'' => {
+                    self.current.quoted = true;
+                    let end = chars[i + 1..]
+                        .iter()
+                        .position(|&c| c == '\'')
+                        .map_or(chars.len(), |p| i + 1 + p);
+                    let text = chars[i + 1..end].iter().collect::<String>();
+                    self.push_literal(&text);
+                    i = end;
+                }
+                '"' => {
+                    in_double = true;
+                    quoted_before = self.current.quoted;
+                    empty_at = false;
+                    other = false;
+                    self.current.quoted = true;
+                }
+                '\\' => {
+                    if let Some(&next) = chars.get(i + 1) {
+                        self.current.quoted = true;
+                        self.push_literal(&next.to_string());
+                        i += 1;
+                    }
+                }
+                '$' => {
+                    i = self.dollar(&chars, i, false)?.0;
+                    continue;
+                }
+                '`' => i = self.backquote(&chars, i, false)?,
+                _ => self.push_unquoted(ch),
+            }
+            i += 1;
+        }
+
+        self.end_field(false);
+        Ok((self.fields, self.state))
+    }
+
+    /// Expand a leading `~` or `~user` when followed by `/` or the end.
+    ///
+    /// # Returns
+    /// - index of the first character left to expand.
+    fn tilde(&mut self, chars: &[char]) -> usize {
+        if chars.first() != Some(&'~') {
+            return 0;
+        }
+        let end = chars.iter().position(|&c| c == '/').unwrap_or(chars.len());
+        let user = chars[1..end].iter().collect::<String>();
+        if !user.chars().all(|c| c.is_ascii_alphanumeric() || "._-".contains(c)) {
+            return 0;
+        }
+        let home = if user.is_empty() {
+            match self.state.get_var("HOME") {
+                Some(home) => home.to_string(),
+                None => self.state.home.display().to_string(),
+            }
+        } else {
+            match get_user_by_name(&user) {
+                Some(user) => user.home_dir().display().to_string(),
+                None => return 0,
+            }
+        };
+        self.current.quoted = true;
+        self.push_literal(&home);
+        end
+    }
+
+    /// Expand the `$` expression starting at `start`.
+    ///
+    /// # Returns
+    /// - index right after the expression, and whether it was a `$@` that
+    ///   expanded to nothing.
+    fn dollar(
+        &mut self,
+        chars: &[char],
+        start: usize,
+        quoted: bool,
+    ) -> Result<(usize, bool), String> {
+        let Some(&next) = chars.get(start + 1) else {
+            self.push_literal("$");
+            return Ok((start + 1, false));
+        };
+
+        if next == '{' {
+            let end = matching_brace(chars, start + 1).ok_or("bad substitution")?;
+            let inner = chars[start + 2..end].iter().collect::<String>();
+            let value = self.braced(&inner)?;
+            self.push_value(value, quoted);
+            return Ok((end + 1, false));
+        }
+
+        if next == '(' {
+            let end = matching_paren(chars, start + 1).ok_or("Missing ')'")?;
+            let source = chars[start + 2..end].iter().collect::<String>();
+            let output = self.substitute(&source)?;
+            self.push_value(Some(output), quoted);
+            return Ok((end + 1, false));
+        }
+
+        if next == '@' || next == '*' {
+            let params = self.state.positional.clone();
+            if next == '@' && quoted {
+                // "$@": one field per parameter
+                for (n, param) in params.iter().enumerate() {
+                    if n > 0 {
+                        self.end_field(true);
+                        self.current.quoted = true;
+                    }
+                    self.push_literal(param);
+                }
+                return Ok((start + 2, params.is_empty()));
+            }
+            if quoted {
+                let ifs = self.state.get_var("IFS").unwrap_or(" \t\n");
+                let sep = ifs.chars().next().map(String::from).unwrap_or_default();
+                self.push_literal(&params.join(&sep));
+            } else {
+                for (n, param) in params.iter().enumerate() {
+                    if n > 0 {
+                        self.end_field(false);
+                    }
+                    self.push_expansion(param);
+                }
+            }
+            return Ok((start + 2, false));
+        }
+
+        let (name, end) = if next.is_ascii_alphabetic() || next == '_' {
+            let len = chars[start + 1..]
+                .iter()
+                .take_while(|c| c.is_ascii_alphanumeric() || **c == '_')
+                .count();
+            (chars[start + 1..start + 1 + len].iter().collect(), start + 1 + len)
+        } else if next.is_ascii_digit() || "?$#!-".contains(next) {
+            (next.to_string(), start + 2)
+        } else {
+            self.push_literal("$");
+            return Ok((start + 1, false));
+        };
+        let value = self.parameter(&name);
+        self.push_value(value, quoted);
+        Ok((end, false))
+    }
+
+    /// Expand the backquoted command starting at `start`. Inside the
+    /// backquotes, a backslash only escapes `$`, `` ` ``, `\` and, when the
+    /// backquotes are double quoted, `"`.
+    ///
+    /// # Returns
+    /// - index of the closing backquote.
+    fn backquote(&mut self, chars: &[char], start: usize, quoted: bool) -> Result<usize, String> {
+        let end = matching_backquote(chars, start).ok_or("EOF in backquote substitution")?;
+        let mut source = String::new();
+        let mut i = start + 1;
+        while i < end {
+            let ch = chars[i];
+            let next = chars[i + 1];
+            if ch == '\\' && ("$`\\".contains(next) || (quoted && next == '"')) {
+                source.push(next);
+                i += 1;
+            } else {
+                source.push(ch);
+            }
+            i += 1;
+        }
+        let output = self.substitute(&source)?;
+        self.push_value(Some(output), quoted);
+        Ok(end)
+    }
+
+    /// Run `source` as a command list and return its output without the
+    /// trailing newlines. Its status becomes `$?`.
+    fn substitute(&mut self, source: &str) -> Result<String, String> {
+        let list = source.to_string().custom_split().map_err(|err| match err {
+            ParseError::Incomplete(message) | ParseError::Syntax(message) => message,
+        })?;
+        let (output, status) = capture_output(&list, self.state)?;
+        self.state.last_command_staus = status;
+        self.state.substitution_status = Some(status);
+        Ok(output.trim_end_matches('\n').to_string())
+    }
+
+    /// Append the value of an expansion, unless the parameter is unset.
+    fn push_value(&mut self, value: Option<String>, quoted: bool) {
+        let value = value.unwrap_or_default();
+        if quoted {
+            self.push_literal(&value);
+        } else {
+            self.push_expansion(&value);
+        }
+    }
+
+    /// Return the value of a named, positional or special parameter.
+    fn parameter(&self, name: &str) -> Option<String> {
+        let state = &self.state;
+        match name {
+            "?" => Some(state.last_command_staus.to_string()),
+            "$" => Some(state.pid.to_string()),
+            "!" => state.last_background_pid.map(|pid| pid.to_string()),
+            "#" => Some(state.positional.len().to_string()),
+            "-" => Some(state.options.letters()),
+            "0" => Some(state.arg0.clone()),
+            "@" | "*" => Some(state.positional.join(" ")),
+            _ => match name.parse::<usize>() {
+                // `${00}` names `$0` too
+                Ok(0) => Some(state.arg0.clone()),
+                Ok(n) => state.positional.get(n - 1).cloned(),
+                Err(_) => state.get_var(name).map(str::to_string),
+            },
+        }
+    }
+
+    /// Expand the inside of `${...}`: `NAME`, `#NAME` (length) and
+    /// `NAME` followed by one of the operators `-`, `=`, `+`, `:-`, `:=`, `:+`.
+    fn braced(&mut self, inner: &str) -> Result<Option<String>, String> {
+        let bad = || format!("${{{}}}: bad substitution", inner);
+        if let Some(name) = inner.strip_prefix('#')
+            && !name.is_empty()
+        {
+            if !is_parameter(name) {
+                return Err(bad());
+            }
+            let len = self.parameter(name).unwrap_or_default().chars().count();
+            return Ok(Some(len.to_string()));
+        }
+
+        let name_len = if inner.starts_with(|c: char| c.is_ascii_digit()) {
+            inner.chars().take_while(|c| c.is_ascii_digit()).count()
+        } else if inner.starts_with(|c: char| "?$#!-@*".contains(c)) {
+            1
+        } else {
+            inner
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+                .count()
+        };
+        let (name, rest) = inner.split_at(name_len);
+        if name.is_empty() {
+            return Err(bad());
+        }
+        let value = self.parameter(name);
+        if rest.is_empty() {
+            return Ok(value);
+        }
+
+        let (colon, rest) = match rest.strip_prefix(':') {
+            Some(rest) => (true, rest),
+            None => (false, rest),
+        };
+        let mut rest = rest.chars();
+        let op = rest.next();
+        let word = rest.as_str();
+        // With a colon, an empty value counts as unset
+        let set = value.as_ref().is_some_and(|v| !colon || !v.is_empty());
+        match op {
+            Some('-') if set => Ok(value),
+            Some('-') => Ok(Some(expand_string(word, self.state)?)),
+            Some('+') if set => Ok(Some(expand_string(word, self.state)?)),
+            Some('+') => Ok(None),
+            Some('=') if set => Ok(value),
+            Some('=') => {
+                if !is_name(name) {
+                    return Err(format!("{}: cannot assign in this way", name));
+                }
+                let word = expand_string(word, self.state)?;
+                self.state.set_var(name, &word);
+                Ok(Some(word))
+            }
+            _ => Err(bad()),
+        }
+    }
+}
+
+/// Builtins whose `NAME=value` arguments are expanded like assignments.
+const DECLARATIONS: &[&str] = &["export", "local"];
+
+/// Return `true` if `name` names a parameter: a variable, a positional
+/// parameter or a special one.
+fn is_parameter(name: &str) -> bool {
+    is_name(name)
+        || name.chars().all(|c| c.is_ascii_digit())
+        || (name.len() == 1 && "?$#!-@*".contains(name))
+}
+
+/// Expand a raw word into fields: tilde, parameter and command expansion,
+/// field splitting of unquoted results, pathname expansion and quote
+/// removal.
+///
+/// # Parameters
+/// - `word`: raw word as produced by `tokenize`.
+/// - `state`: shell state providing the variables.
+///
+/// # Returns
+/// - the resulting fields, possibly none, or an error message such as
+///   `bad substitution` or, with `failglob`, `no match`.
+pub fn expand_word(word: &str, state: &mut ShellState) -> Result<Vec<String>, String> {
+    Expander::new(state, true).expand(word)
+}
+
+/// Expand a raw word into a single string, without field splitting nor
+/// pathname expansion, as done for assignment values.
+pub fn expand_string(word: &str, state: &mut ShellState) -> Result<String, String> {
+    let fields = Expander::new(state, false).expand(word)?;
+    Ok(fields.join(" "))
+}
+
+/// Expand a raw word into a pattern, as done for the patterns of `case`:
+/// like `expand_string`, but the characters that were quoted are escaped
+/// so they only match themselves.
+pub fn expand_pattern(word: &str, state: &mut ShellState) -> Result<String, String> {
+    let (fields, _) = Expander::new(state, false).fields(word)?;
+    let patterns = fields.into_iter().map(|field| field.pattern);
+    Ok(patterns.collect::<Vec<_>>().join(" "))
+}
+
+/// Expand the targets of redirections, each to a single field.
+///
+/// # Returns
+/// - the expanded redirections, or an error message when an expansion
+///   fails or a target does not expand to exactly one field.
+pub fn expand_redirects(
+    redirects: &[Redirect],
+    state: &mut ShellState,
+) -> Result<Vec<Redirect>, String> {
+    let mut expanded = Vec::new();
+    for redirect in redirects {
+        let mut target = expand_word(&redirect.target, state)?;
+        if target.len() != 1 {
+            return Err(format!("{}: ambiguous redirect", redirect.target));
+        }
+        expanded.push(Redirect {
+            target: target.remove(0),
+            ..redirect.clone()
+        });
+    }
+    Ok(expanded)
+}
+
+/// Expand every raw word of a parsed command.
+///
+/// # Parameters
+/// - `command`: command with raw words, as produced by `custom_split`.
+/// - `state`: shell state providing the variables.
+///
+/// # Returns
+/// - a command whose `name` is the first resulting field, or an error
+///   message when an expansion fails or a redirection target does not
+///   expand to exactly one field.
+pub fn expand_command(command: &Command, state: &mut ShellState) -> Result<Command, String> {
+    state.substitution_status = None;
+    let mut fields = Vec::new();
+    for word in std::iter::once(&command.name).chain(&command.args) {
+        if word.is_empty() {
+            continue;
+        }
+        // Like a plain `NAME=$value`, assignments given to `export` are not
+        // field split
+        if DECLARATIONS.contains(&command.name.as_str())
+            && let Some((name, value)) = split_assignment(word)
+        {
+            fields.push(format!("{}={}", name, expand_string(value, state)?));
+        } else {
+            fields.extend(expand_word(word, state)?);
+        }
+    }
+
+    let mut assignments = Vec::new();
+    for (name, value) in &command.assignments {
+        assignments.push((name.clone(), expand_string(value, state)?));
+    }
+
+    let redirects = expand_redirects(&command.redirects, state)?;
+
+    let name = if fields.is_empty() {
+        String::new()
+    } else {
+        fields.remove(0)
+    };
+    Ok(Command {
+        name,
+        args: fields,
+        redirects,
+        assignments,
+    })
+}
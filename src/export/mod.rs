@@ -0,0 +1,65 @@
+use crate::{Builtin, ShellState, Streams, is_name, quote, write_error};
+use std::io::Write;
+
+/// Mark variables as exported so they are passed to the programs the shell
+/// runs, or list the exported variables.
+///
+/// # Parameters
+/// - `args`: `NAME=value` or `NAME` words; none or `-p` lists the variables.
+/// - `state`: shell state holding the variables.
+/// - `stdout`: stream the listing is written to.
+/// - `stderr`: stream errors are written to.
+///
+/// # Returns
+/// - `0` on success, `1` if a name is not a valid identifier.
+pub fn export(
+    args: &[String],
+    state: &mut ShellState,
+    stdout: &mut dyn Write,
+    stderr: &mut dyn Write,
+) -> i32 {
+    if args.is_empty() || args == ["-p"] {
+        let mut names: Vec<&String> = state
+            .vars
+            .iter()
+            .filter(|(_, var)| var.exported)
+            .map(|(name, _)| name)
+            .collect();
+        names.sort();
+        for name in names {
+            let value = &state.vars[name].value;
+            if writeln!(stdout, "export {}={}", name, quote(value)).is_err() {
+                return 1;
+            }
+        }
+        return 0;
+    }
+
+    let mut status = 0;
+    for arg in args {
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (arg.as_str(), None),
+        };
+        if !is_name(name) {
+            write_error(stderr, &format!("export: `{}': not a valid identifier", arg));
+            status = 1;
+            continue;
+        }
+        state.export_var(name, value);
+    }
+    status
+}
+
+/// `export` builtin, see [`export`].
+pub struct ExportBuiltin;
+
+impl Builtin for ExportBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        export(args, state, io.stdout, io.stderr)
+    }
+
+    fn flags(&self) -> &str {
+        "p"
+    }
+}
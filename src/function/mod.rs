@@ -0,0 +1,184 @@
+use crate::{Builtin, Flow, Function, ShellState, Streams, exec_redirected, is_name, write_error};
+use std::collections::HashMap;
+use std::io::Write;
+
+/// Run a function with `args` as its positional parameters. The variables
+/// it declares `local` get their previous value back when it returns, and
+/// the loops of the caller cannot be left from inside it.
+///
+/// # Returns
+/// - the status given to `return`, otherwise the exit status of the last
+///   command of the body.
+pub fn call_function(function: &Function, args: &[String], state: &mut ShellState) -> i32 {
+    let positional = std::mem::replace(&mut state.positional, args.to_vec());
+    let loop_depth = std::mem::take(&mut state.loop_depth);
+    state.scopes.push(HashMap::new());
+
+    let status = exec_redirected(&function.body, &function.redirects, state);
+    if state.flow == Some(Flow::Return) {
+        state.flow = None;
+    }
+
+    for (name, var) in state.scopes.pop().unwrap_or_default() {
+        match var {
+            Some(var) => state.vars.insert(name, var),
+            None => state.vars.remove(&name),
+        };
+    }
+    state.loop_depth = loop_depth;
+    state.positional = positional;
+    status
+}
+
+/// Declare variables local to the function being run.
+///
+/// # Parameters
+/// - `args`: `NAME=value` words, or `NAME` to unset the variable until the
+///   function returns.
+/// - `state`: shell state holding the variables.
+/// - `stderr`: stream errors are written to.
+///
+/// # Returns
+/// - `0` on success, `1` outside of a function or if a name is not a
+///   valid identifier.
+pub fn local(args: &[String], state: &mut ShellState, stderr: &mut dyn Write) -> i32 {
+    if state.scopes.is_empty() {
+        write_error(stderr, "local: not in a function");
+        return 1;
+    }
+    let mut status = 0;
+    for arg in args {
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (arg.as_str(), None),
+        };
+        if !is_name(name) {
+            write_error(stderr, &format!("local: `{}': not a valid identifier", arg));
+            status = 1;
+            continue;
+        }
+        let previous = state.vars.get(name).cloned();
+        if let Some(scope) = state.scopes.last_mut() {
+            scope.entry(name.to_string()).or_insert(previous);
+        }
+        match value {
+            Some(value) => state.set_var(name, value),
+            None => state.unset_var(name),
+        }
+    }
+    status
+}
+
+/// Leave the function or the sourced file being run.
+///
+/// # Parameters
+/// - `args`: optional status to return, `$?` by default.
+/// - `state`: shell state the jump is recorded in.
+/// - `stderr`: stream errors are written to.
+///
+/// # Returns
+/// - the status to return, `1` outside of a function or sourced file, or
+///   `2` when the status is not a number.
+pub fn return_from(args: &[String], state: &mut ShellState, stderr: &mut dyn Write) -> i32 {
+    if state.scopes.is_empty() && state.source_depth == 0 {
+        let message = "return: can only `return' from a function or sourced script";
+        write_error(stderr, message);
+        return 1;
+    }
+    let status = match args.first() {
+        None => state.last_command_staus,
+        Some(arg) => match arg.parse::<i32>() {
+            Ok(status) => status,
+            Err(_) => {
+                write_error(stderr, &format!("return: Illegal number: {}", arg));
+                return 2;
+            }
+        },
+    };
+    state.flow = Some(Flow::Return);
+    status
+}
+
+/// Print functions back as shell code that defines them again.
+///
+/// # Parameters
+/// - `args`: `-f`, or `-F` to only print `declare -f name` lines, followed
+///   by the names of the functions, every function by default.
+/// - `state`: shell state holding the functions.
+/// - `stdout`: stream the functions are printed to.
+/// - `stderr`: stream errors are written to.
+///
+/// # Returns
+/// - `0` on success, `1` when a name is not a function, `2` without `-f`
+///   or `-F`.
+pub fn declare(
+    args: &[String],
+    state: &mut ShellState,
+    stdout: &mut dyn Write,
+    stderr: &mut dyn Write,
+) -> i32 {
+    let (names_only, names) = match args.first().map(String::as_str) {
+        Some("-f") => (false, &args[1..]),
+        Some("-F") => (true, &args[1..]),
+        _ => {
+            write_error(stderr, "declare: usage: declare -f|-F [name ...]");
+            return 2;
+        }
+    };
+    let mut listed = if names.is_empty() {
+        state.functions.keys().cloned().collect::<Vec<_>>()
+    } else {
+        names.to_vec()
+    };
+    if names.is_empty() {
+        listed.sort();
+    }
+
+    let mut status = 0;
+    for name in listed {
+        let Some(function) = state.functions.get(&name) else {
+            status = 1;
+            continue;
+        };
+        let written = if names_only {
+            writeln!(stdout, "declare -f {}", name)
+        } else {
+            writeln!(stdout, "{}", function)
+        };
+        if written.is_err() {
+            return 1;
+        }
+    }
+    status
+}
+
+/// `local` builtin, see [`local`].
+pub struct LocalBuiltin;
+
+impl Builtin for LocalBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        local(args, state, io.stderr)
+    }
+}
+
+/// `return` builtin, see [`return_from`].
+pub struct ReturnBuiltin;
+
+impl Builtin for ReturnBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        return_from(args, state, io.stderr)
+    }
+}
+
+/// `declare` builtin, see [`declare`].
+pub struct DeclareBuiltin;
+
+impl Builtin for DeclareBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        declare(args, state, io.stdout, io.stderr)
+    }
+
+    fn flags(&self) -> &str {
+        "fF"
+    }
+}
@@ -0,0 +1,243 @@
+use crate::*;
+use std::fs;
+use std::path::Path;
+
+/// Match `ch` against the bracket expression starting with the `[` at
+/// `start`, such as `[a-z]`, `[!0-9]` or `[[:alpha:]_]`.
+///
+/// # Returns
+/// - whether `ch` is matched and the index right after the closing `]`, or
+///   `None` when the expression is not closed and `[` is a literal.
+fn match_bracket(pattern: &[char], start: usize, ch: char) -> Option<(bool, usize)> {
+    let mut i = start + 1;
+    let negate = matches!(pattern.get(i), Some('!' | '^'));
+    if negate {
+        i += 1;
+    }
+    let mut matched = false;
+    let mut first = true;
+    while i < pattern.len() {
+        let c = pattern[i];
+        if c == ']' && !first {
+            return Some((matched != negate, i + 1));
+        }
+        first = false;
+
+        if c == '[' && pattern.get(i + 1) == Some(&':') {
+            let rest = pattern[i + 2..].iter().collect::<String>();
+            if let Some(end) = rest.find(":]") {
+                let class = &rest[..end];
+                matched |= match class {
+                    "alnum" => ch.is_alphanumeric(),
+                    "alpha" => ch.is_alphabetic(),
+                    "blank" => ch == ' ' || ch == '\t',
+                    "digit" => ch.is_ascii_digit(),
+                    "lower" => ch.is_lowercase(),
+                    "upper" => ch.is_uppercase(),
+                    "punct" => ch.is_ascii_punctuation(),
+                    "space" => ch.is_whitespace(),
+                    "xdigit" => ch.is_ascii_hexdigit(),
+                    _ => false,
+                };
+                i += 2 + class.chars().count() + 2;
+                continue;
+            }
+        }
+
+        let (low, next) = match c {
+            '\\' if i + 1 < pattern.len() => (pattern[i + 1], i + 2),
+            _ => (c, i + 1),
+        };
+        i = next;
+        // A `-` right before the closing `]` is a literal
+        if pattern.get(i) == Some(&'-') && pattern.get(i + 1).is_some_and(|&c| c != ']') {
+            let (high, next) = match pattern[i + 1] {
+                '\\' if i + 2 < pattern.len() => (pattern[i + 2], i + 3),
+                c => (c, i + 2),
+            };
+            matched |= low <= ch && ch <= high;
+            i = next;
+        } else {
+            matched |= ch == low;
+        }
+    }
+    None
+}
+
+/// Match the whole `text` against `pattern`, backtracking on the last `*`.
+fn match_chars(pattern: &[char], text: &[char]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        let step = match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+                continue;
+            }
+            Some('?') => Some(p + 1),
+            Some('[') => match match_bracket(pattern, p, text[t]) {
+                Some((true, next)) => Some(next),
+                Some((false, _)) => None,
+                None => (text[t] == '[').then_some(p + 1),
+            },
+            Some('\\') if p + 1 < pattern.len() => (pattern[p + 1] == text[t]).then_some(p + 2),
+            Some(&c) => (c == text[t]).then_some(p + 1),
+            None => None,
+        };
+        match step {
+            Some(next) => {
+                p = next;
+                t += 1;
+            }
+            None => match star {
+                // Let the last `*` swallow one more character
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Return `true` if the whole `text` matches the shell `pattern`: `*`
+/// matches any string, `?` any character, `[...]` one character of a set,
+/// and a backslash makes the next character literal.
+pub fn pattern_matches(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    match_chars(&pattern, &text)
+}
+
+/// Return `true` if `pattern` holds an unescaped `*`, `?` or bracket
+/// expression, so it is subject to pathname expansion.
+pub fn has_glob(pattern: &str) -> bool {
+    let chars = pattern.chars().collect::<Vec<_>>();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '*' | '?' => return true,
+            '[' if match_bracket(&chars, i, '\0').is_some() => return true,
+            _ => {}
+        }
+        i += 1;
+    }
+    false
+}
+
+/// Remove the backslashes escaping the characters of a pattern.
+fn unescape(pattern: &str) -> String {
+    let mut text = String::new();
+    let mut chars = pattern.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => text.extend(chars.next()),
+            _ => text.push(ch),
+        }
+    }
+    text
+}
+
+/// Return the entries of `dir` whose name matches `component`. Hidden
+/// entries only match a component starting with a `.`.
+fn match_entries(dir: &Path, component: &str) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let hidden = component.starts_with('.') || component.starts_with("\\.");
+    entries
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| hidden || !name.starts_with('.'))
+        .filter(|name| pattern_matches(component, name))
+        .collect()
+}
+
+/// Return every path below `prefix`, without hidden entries and without
+/// following symbolic links, as matched by `**`.
+fn walk(root: &Path, prefix: &str, found: &mut Vec<String>) {
+    for name in match_entries(&root.join(prefix), "*") {
+        let path = format!("{}{}", prefix, name);
+        let is_dir = fs::symlink_metadata(root.join(&path)).is_ok_and(|meta| meta.is_dir());
+        found.push(path.clone());
+        if is_dir {
+            walk(root, &format!("{}/", path), found);
+        }
+    }
+}
+
+/// Expand a pathname pattern into the paths matching it.
+///
+/// The pattern is matched one `/`-separated component at a time; a `*`
+/// never matches a `/`. With `globstar`, a `**` component matches any
+/// number of directories.
+///
+/// # Parameters
+/// - `pattern`: pattern with quoted characters escaped by a backslash.
+/// - `current_dir`: base directory for relative patterns.
+/// - `globstar`: whether `**` is recursive.
+///
+/// # Returns
+/// - the matching paths, written relative like the pattern and sorted like
+///   `ls` sorts, possibly none.
+pub fn glob(pattern: &str, current_dir: &Path, globstar: bool) -> Vec<String> {
+    let root = current_dir.to_path_buf();
+    let (mut paths, rest) = match pattern.strip_prefix('/') {
+        Some(rest) => (vec!["/".to_string()], rest),
+        None => (vec![String::new()], pattern),
+    };
+    let components = rest.split('/').collect::<Vec<_>>();
+
+    for (i, component) in components.iter().enumerate() {
+        let is_last = i == components.len() - 1;
+        let mut next = Vec::new();
+        for prefix in &paths {
+            if component.is_empty() {
+                // `a//b` or a trailing `/`, which only keeps directories
+                if root.join(prefix).is_dir() {
+                    next.push(prefix.clone());
+                }
+                continue;
+            }
+            if globstar && *component == "**" {
+                let mut found = Vec::new();
+                walk(&root, prefix, &mut found);
+                if is_last {
+                    next.extend(found);
+                } else {
+                    next.push(prefix.clone());
+                    next.extend(found.into_iter().filter(|path| root.join(path).is_dir()));
+                }
+                continue;
+            }
+            if has_glob(component) {
+                let names = match_entries(&root.join(prefix), component);
+                next.extend(names.into_iter().map(|name| format!("{}{}", prefix, name)));
+            } else {
+                let path = format!("{}{}", prefix, unescape(component));
+                if fs::symlink_metadata(root.join(&path)).is_ok() {
+                    next.push(path);
+                }
+            }
+        }
+        paths = next;
+        if !is_last {
+            paths.retain(|path| root.join(path).is_dir());
+            for path in paths.iter_mut() {
+                if !path.is_empty() && !path.ends_with('/') {
+                    path.push('/');
+                }
+            }
+        }
+    }
+
+    paths.retain(|path| !path.is_empty());
+    paths.sort_by(|a, b| compare_names(a, b));
+    paths.dedup();
+    paths
+}
@@ -0,0 +1,262 @@
+use crate::{Token, tokenize};
+
+/// Characters ending the prefix of a `!prefix` event.
+const EVENT_END: &str = ":;&|<>()'\"";
+
+/// Return the entry `n` of `hist`, counting from 1.
+fn event_number(hist: &[String], n: usize) -> Option<&String> {
+    hist.get(n.checked_sub(1)?)
+}
+
+/// Split a history entry into words, operators being words of their own.
+fn entry_words(entry: &str) -> Vec<String> {
+    match tokenize(entry) {
+        Ok(tokens) => tokens
+            .iter()
+            .filter(|token| **token != Token::Newline)
+            .map(Token::text)
+            .collect(),
+        Err(_) => entry.split_whitespace().map(str::to_string).collect(),
+    }
+}
+
+/// Parse a word index of a designator: a number, `^` (1) or `$` (last).
+///
+/// # Returns
+/// - the index and the position right after it.
+fn word_index(chars: &[char], start: usize, last: usize) -> Option<(usize, usize)> {
+    match chars.get(start) {
+        Some('^') => Some((1, start + 1)),
+        Some('$') => Some((last, start + 1)),
+        Some(c) if c.is_ascii_digit() => {
+            let len = chars[start..]
+                .iter()
+                .take_while(|c| c.is_ascii_digit())
+                .count();
+            let digits = chars[start..start + len].iter().collect::<String>();
+            Some((digits.parse().ok()?, start + len))
+        }
+        _ => None,
+    }
+}
+
+/// Select words of `entry` with the designator starting at `start`: `n`,
+/// `^`, `$`, `*`, `x-y`, `x-` (up to the word before the last) or `x*`.
+///
+/// # Returns
+/// - the selected words joined with spaces and the position right after
+///   the designator, or `None` when it is not a valid designator.
+fn designate(entry: &str, chars: &[char], start: usize) -> Option<Result<(String, usize), ()>> {
+    let words = entry_words(entry);
+    let last = words.len().saturating_sub(1);
+    let select = |from: usize, to: usize| match words.get(from..=to) {
+        Some(words) => Ok(words.join(" ")),
+        None if from == to + 1 => Ok(String::new()),
+        None => Err(()),
+    };
+
+    if chars.get(start) == Some(&'*') {
+        return Some(select(1, last).map(|words| (words, start + 1)));
+    }
+    let (from, next) = word_index(chars, start, last)?;
+    let range = match chars.get(next) {
+        Some('*') => select(from, last).map(|words| (words, next + 1)),
+        Some('-') => match word_index(chars, next + 1, last) {
+            Some((to, end)) => select(from, to).map(|words| (words, end)),
+            None => select(from, last.saturating_sub(1)).map(|words| (words, next + 1)),
+        },
+        _ => select(from, from).map(|words| (words, next)),
+    };
+    Some(range)
+}
+
+/// Find the entry referred to by the event starting at `start` (right
+/// after the `!`): `!` (previous entry), `n`, `-n`, `?string?` or a prefix.
+/// A designator right after the `!` (`!$`, `!:2`, `!*`) refers to the
+/// previous entry.
+///
+/// # Returns
+/// - the entry and the position right after the event, or the error
+///   message for an event that does not exist.
+fn find_event<'a>(
+    hist: &'a [String],
+    chars: &[char],
+    start: usize,
+) -> Result<(&'a String, usize), String> {
+    let not_found = |end: usize| {
+        let event = chars[start - 1..end].iter().collect::<String>();
+        format!("{}: event not found", event)
+    };
+    let number_len = |from: usize| {
+        chars[from..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .count()
+    };
+
+    match chars[start] {
+        '!' => hist
+            .last()
+            .map(|e| (e, start + 1))
+            .ok_or_else(|| not_found(start + 1)),
+        '$' | '^' | '*' | ':' => hist
+            .last()
+            .map(|e| (e, start))
+            .ok_or_else(|| not_found(start + 1)),
+        c if c.is_ascii_digit() => {
+            let end = start + number_len(start);
+            let n = chars[start..end]
+                .iter()
+                .collect::<String>()
+                .parse()
+                .unwrap_or(0);
+            event_number(hist, n)
+                .map(|e| (e, end))
+                .ok_or_else(|| not_found(end))
+        }
+        '-' if chars.get(start + 1).is_some_and(char::is_ascii_digit) => {
+            let end = start + 1 + number_len(start + 1);
+            let n: usize = chars[start + 1..end]
+                .iter()
+                .collect::<String>()
+                .parse()
+                .unwrap_or(0);
+            hist.len()
+                .checked_sub(n)
+                .and_then(|index| hist.get(index))
+                .map(|e| (e, end))
+                .ok_or_else(|| not_found(end))
+        }
+        '?' => {
+            let end = chars[start + 1..]
+                .iter()
+                .position(|&c| c == '?' || c == '\n')
+                .map_or(chars.len(), |p| start + 1 + p);
+            let search = chars[start + 1..end].iter().collect::<String>();
+            let end = if chars.get(end) == Some(&'?') {
+                end + 1
+            } else {
+                end
+            };
+            hist.iter()
+                .rev()
+                .find(|entry| entry.contains(&search))
+                .map(|e| (e, end))
+                .ok_or_else(|| not_found(end))
+        }
+        _ => {
+            let len = chars[start..]
+                .iter()
+                .take_while(|c| !c.is_whitespace() && !EVENT_END.contains(**c))
+                .count();
+            let prefix = chars[start..start + len].iter().collect::<String>();
+            hist.iter()
+                .rev()
+                .find(|entry| entry.starts_with(&prefix))
+                .map(|e| (e, start + len))
+                .ok_or_else(|| not_found(start + len))
+        }
+    }
+}
+
+/// Error for the invalid designator starting at `start`, in the history
+/// reference starting at `bang`.
+fn bad_specifier(chars: &[char], bang: usize, start: usize) -> String {
+    let len = chars[start..]
+        .iter()
+        .take_while(|c| c.is_ascii_digit() || "^$*-".contains(**c))
+        .count();
+    let spec = chars[bang..start + len].iter().collect::<String>();
+    format!("{}: bad word specifier", spec)
+}
+
+/// Apply a `^old^new^` quick substitution to the previous entry.
+fn quick_substitution(line: &str, hist: &[String]) -> Result<String, String> {
+    let body = line[1..].trim_end_matches('\n');
+    let mut parts = body.splitn(3, '^');
+    let old = parts.next().unwrap_or_default();
+    let new = parts.next().unwrap_or_default();
+    let rest = parts.next().unwrap_or_default();
+    let previous = hist.last().ok_or("!!: event not found")?;
+    if old.is_empty() || !previous.contains(old) {
+        return Err(format!("^{}^{}: substitution failed", old, new));
+    }
+    Ok(format!("{}{}\n", previous.replacen(old, new, 1), rest))
+}
+
+/// Apply csh-style history expansion to a line read from the user.
+///
+/// Supports `!!`, `!n`, `!-n`, `!prefix`, `!?string?` and `^old^new^`,
+/// optionally followed by a word designator (`!$`, `!^`, `!*`, `!:2`,
+/// `!:1-3`, `!!:$`...). Nothing is expanded between single quotes, after a
+/// backslash, in `$!`, or when the `!` is followed by a blank, `=` or a
+/// character ending the prefix of an event, such as `;` or `)`, which
+/// would leave the prefix empty.
+///
+/// # Parameters
+/// - `line`: line as typed.
+/// - `hist`: history entries, oldest first.
+///
+/// # Returns
+/// - `Ok(Some(line))` with the expanded line when something was expanded,
+///   `Ok(None)` when the line is unchanged, or the error message, such as
+///   `!foo: event not found`.
+pub fn expand_history(line: &str, hist: &[String]) -> Result<Option<String>, String> {
+    if line.starts_with('^') {
+        return quick_substitution(line, hist).map(Some);
+    }
+
+    let chars = line.chars().collect::<Vec<_>>();
+    let mut expanded = String::new();
+    let mut changed = false;
+    let mut in_single = false;
+    let mut in_double = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let ch = chars[i];
+        match ch {
+            '\'' if !in_double => in_single = !in_single,
+            '"' if !in_single => in_double = !in_double,
+            '\\' if !in_single && i + 1 < chars.len() => {
+                expanded.push(ch);
+                expanded.push(chars[i + 1]);
+                i += 2;
+                continue;
+            }
+            '!' if !in_single => {
+                let literal = match chars.get(i + 1) {
+                    _ if i > 0 && chars[i - 1] == '$' => true,
+                    None => true,
+                    Some(&next) => {
+                        next.is_whitespace()
+                            || next == '='
+                            || (next != ':' && EVENT_END.contains(next))
+                    }
+                };
+                if !literal {
+                    let (entry, next) = find_event(hist, &chars, i + 1)?;
+                    let (text, next) = match chars.get(next) {
+                        Some(':') => match designate(entry, &chars, next + 1) {
+                            Some(Ok(words)) => words,
+                            Some(Err(())) => return Err(bad_specifier(&chars, i, next + 1)),
+                            None => (entry.clone(), next),
+                        },
+                        Some('^' | '$' | '*') => match designate(entry, &chars, next) {
+                            Some(Ok(words)) => words,
+                            _ => return Err(bad_specifier(&chars, i, next)),
+                        },
+                        _ => (entry.clone(), next),
+                    };
+                    expanded.push_str(&text);
+                    changed = true;
+                    i = next;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        expanded.push(ch);
+        i += 1;
+    }
+    Ok(changed.then_some(expanded))
+}
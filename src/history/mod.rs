@@ -1,19 +1,507 @@
-/// Print the session command history with line numbers.
+use crate::{Builtin, Candidate, ShellState, Streams, pattern_matches, write_error};
+pub use expansion::*;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Read;
+use std::io::Seek;
+use std::io::Write;
+use std::os::fd::AsRawFd;
+use std::path::Path;
+use std::path::PathBuf;
+
+pub mod expansion;
+
+/// Commands entered in the shell, oldest first.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    entries: Vec<String>,
+    unsaved: usize, // Number of trailing entries not yet written to a history file
+}
+
+impl History {
+    /// Return every entry, oldest first.
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Return the number of entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Return `true` when there is no entry.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Add an entry unless `control` says otherwise, then drop the oldest
+    /// entries beyond `size`.
+    ///
+    /// # Parameters
+    /// - `entry`: command line, without its trailing newline.
+    /// - `control`: `HISTCONTROL` value, a `:` separated list of
+    ///   `ignorespace` (skip entries starting with a space), `ignoredups`
+    ///   (skip an entry equal to the previous one), `ignoreboth` (both) and
+    ///   `erasedups` (remove older copies of the entry).
+    /// - `size`: maximum number of entries, `None` for no limit.
+    ///
+    /// # Returns
+    /// - `true` if the entry was added.
+    pub fn push(&mut self, entry: &str, control: &str, size: Option<usize>) -> bool {
+        let control = control.split(':').collect::<Vec<_>>();
+        let ignore_space = control.contains(&"ignorespace") || control.contains(&"ignoreboth");
+        let ignore_dups = control.contains(&"ignoredups") || control.contains(&"ignoreboth");
+        if ignore_space && entry.starts_with(' ') {
+            return false;
+        }
+        if ignore_dups && self.entries.last().is_some_and(|last| last == entry) {
+            return false;
+        }
+        if control.contains(&"erasedups") {
+            let first_unsaved = self.entries.len() - self.unsaved;
+            let erased = self.entries[first_unsaved..]
+                .iter()
+                .filter(|old| *old == entry);
+            self.unsaved -= erased.count();
+            self.entries.retain(|old| old != entry);
+        }
+        self.entries.push(entry.to_string());
+        self.unsaved += 1;
+        if let Some(size) = size {
+            self.truncate(size);
+        }
+        true
+    }
+
+    /// Keep only the `size` most recent entries.
+    pub fn truncate(&mut self, size: usize) {
+        let extra = self.entries.len().saturating_sub(size);
+        self.entries.drain(..extra);
+        self.unsaved = self.unsaved.min(self.entries.len());
+    }
+
+    /// Add the entries read from a history file, without any filtering.
+    /// They go before the entries not yet written to a history file, which
+    /// stay to be written.
+    pub fn extend(&mut self, entries: Vec<String>) {
+        let first_unsaved = self.entries.len() - self.unsaved;
+        self.entries.splice(first_unsaved..first_unsaved, entries);
+    }
+
+    /// Remove every entry.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.unsaved = 0;
+    }
+
+    /// Remove the entry at `index`, counting from 0.
+    ///
+    /// # Returns
+    /// - the removed entry, or `None` when `index` is out of range.
+    pub fn remove(&mut self, index: usize) -> Option<String> {
+        if index >= self.entries.len() {
+            return None;
+        }
+        if index >= self.entries.len() - self.unsaved {
+            self.unsaved -= 1;
+        }
+        Some(self.entries.remove(index))
+    }
+
+    /// Return the entries added since the history file was last written.
+    pub fn unsaved(&self) -> &[String] {
+        &self.entries[self.entries.len() - self.unsaved..]
+    }
+
+    /// Consider every entry as written to the history file.
+    pub fn mark_saved(&mut self) {
+        self.unsaved = 0;
+    }
+}
+
+/// Write an entry on a single line of the history file: backslashes are
+/// doubled and newlines written as `\n`.
+fn encode(entry: &str) -> String {
+    entry.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+/// Read back an entry written by `encode`.
+fn decode(line: &str) -> String {
+    let mut entry = String::new();
+    let mut chars = line.chars();
+    while let Some(ch) = chars.next() {
+        match (ch, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                entry.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                entry.push('\\');
+                chars.next();
+            }
+            _ => entry.push(ch),
+        }
+    }
+    entry
+}
+
+/// Lock a history file for the lifetime of `file`, so several shells can
+/// use the same file at once.
+fn lock(file: &File, operation: i32) -> io::Result<()> {
+    while unsafe { libc::flock(file.as_raw_fd(), operation) } == -1 {
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+    Ok(())
+}
+
+/// Read every entry of the history file at `path`.
+///
+/// # Returns
+/// - the entries, oldest first, or the error met opening or reading the file.
+pub fn read_history_file(path: &Path) -> io::Result<Vec<String>> {
+    let mut file = File::open(path)?;
+    lock(&file, libc::LOCK_SH)?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    Ok(content.lines().map(decode).collect())
+}
+
+/// Append entries to the history file at `path`, creating it if needed,
+/// then keep only its `file_size` most recent entries.
 ///
 /// # Parameters
-/// - `hist`: slice of previously executed commands.
+/// - `path`: history file.
+/// - `entries`: entries to append.
+/// - `file_size`: maximum number of entries in the file, `None` for no limit.
+pub fn append_history_file(
+    path: &Path,
+    entries: &[String],
+    file_size: Option<usize>,
+) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(path)?;
+    lock(&file, libc::LOCK_EX)?;
+    let mut content = String::new();
+    for entry in entries {
+        content.push_str(&encode(entry));
+        content.push('\n');
+    }
+    file.write_all(content.as_bytes())?;
+
+    let Some(file_size) = file_size else {
+        return Ok(());
+    };
+    let mut content = String::new();
+    file.rewind()?;
+    file.read_to_string(&mut content)?;
+    let lines = content.lines().collect::<Vec<_>>();
+    if lines.len() > file_size {
+        let kept = lines[lines.len() - file_size..]
+            .iter()
+            .map(|line| format!("{}\n", line));
+        let kept = kept.collect::<String>();
+        // Still under the lock: the file is emptied then rewritten
+        file.set_len(0)?;
+        file.write_all(kept.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Replace the content of the history file at `path` with the
+/// `file_size` most recent `entries`, creating the file if needed.
+///
+/// # Parameters
+/// - `path`: history file.
+/// - `entries`: entries to write.
+/// - `file_size`: maximum number of entries in the file, `None` for no limit.
+pub fn write_history_file(
+    path: &Path,
+    entries: &[String],
+    file_size: Option<usize>,
+) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+    lock(&file, libc::LOCK_EX)?;
+    let skipped = file_size.map_or(0, |size| entries.len().saturating_sub(size));
+    let mut content = String::new();
+    for entry in &entries[skipped..] {
+        content.push_str(&encode(entry));
+        content.push('\n');
+    }
+    // Truncated only once locked, so readers never see a partial file
+    file.set_len(0)?;
+    file.write_all(content.as_bytes())
+}
+
+/// Parse a `HISTSIZE`-like value: a number of entries, where a negative or
+/// invalid value means no limit.
+fn parse_size(value: Option<&str>) -> Option<usize> {
+    value.and_then(|value| value.parse().ok())
+}
+
+/// Return the history file of the shell, from `HISTFILE`. History is not
+/// saved when it is unset or empty.
+fn history_file(state: &ShellState) -> Option<PathBuf> {
+    state
+        .get_var("HISTFILE")
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Load the history file into the shell history at the start of an
+/// interactive session, setting `HISTFILE` (`~/.0shell_history`),
+/// `HISTSIZE` and `HISTFILESIZE` (500) first when they are unset.
+///
+/// # Parameters
+/// - `state`: shell state whose history is filled.
+pub fn load_history(state: &mut ShellState) {
+    if state.get_var("HISTFILE").is_none() {
+        let path = state.home.join(".0shell_history");
+        state.set_var("HISTFILE", &path.display().to_string());
+    }
+    if state.get_var("HISTSIZE").is_none() {
+        state.set_var("HISTSIZE", "500");
+    }
+    if state.get_var("HISTFILESIZE").is_none() {
+        let size = state.get_var("HISTSIZE").unwrap_or("500").to_string();
+        state.set_var("HISTFILESIZE", &size);
+    }
+
+    let Some(path) = history_file(state) else {
+        return;
+    };
+    // A missing history file simply means an empty history
+    if let Ok(entries) = read_history_file(&path) {
+        state.hist.extend(entries);
+    }
+    if let Some(size) = parse_size(state.get_var("HISTSIZE")) {
+        state.hist.truncate(size);
+    }
+}
+
+/// Record a command line about to run: add it to the shell
+/// history according to `HISTCONTROL` and `HISTSIZE`, and append it to the
+/// history file, capped at `HISTFILESIZE` entries.
+///
+/// # Parameters
+/// - `state`: shell state holding the history and its settings.
+/// - `entry`: command line as read, trailing newline included.
+pub fn record_history(state: &mut ShellState, entry: &str) {
+    let entry = entry.trim_end_matches('\n');
+    if entry.trim().is_empty() {
+        return;
+    }
+    let control = state.get_var("HISTCONTROL").unwrap_or_default().to_string();
+    let size = parse_size(state.get_var("HISTSIZE"));
+    if !state.hist.push(entry, &control, size) {
+        return;
+    }
+
+    let Some(path) = history_file(state) else {
+        return;
+    };
+    let file_size = parse_size(state.get_var("HISTFILESIZE"));
+    // Failing to save the history must not prevent using the shell
+    let unsaved = state.hist.unsaved().to_vec();
+    if append_history_file(&path, &unsaved, file_size).is_ok() {
+        state.hist.mark_saved();
+    }
+}
+
+/// Print `(index, entry)` pairs with the history number of each entry
+/// right-aligned on `width` columns.
 ///
 /// # Returns
-/// - `0` on success.
-pub fn history(hist: &[String]) -> i32 {
-    let le = hist.len().to_string().len();
-    print!(
-        "{}",
-        hist.iter()
-            .enumerate()
-            .map(|(index, command)| format!("{:>le$}  {}", index + 1, command))
-            .collect::<Vec<_>>()
-            .join("")
-    );
+/// - `0` on success, `1` if writing fails.
+fn print_entries<'a>(
+    entries: impl Iterator<Item = (usize, &'a String)>,
+    width: usize,
+    stdout: &mut dyn Write,
+) -> i32 {
+    for (index, command) in entries {
+        if writeln!(stdout, "{:>width$}  {}", index + 1, command).is_err() {
+            return 1;
+        }
+    }
     0
 }
+
+/// Resolve a `history -d` position: `n` counts from the first entry, `-n`
+/// from the last one.
+///
+/// # Returns
+/// - the index of the entry, counting from 0.
+fn position(arg: &str, len: usize) -> Option<usize> {
+    let n: i64 = arg.parse().ok()?;
+    let index = if n < 0 { len as i64 + n } else { n - 1 };
+    (0..len as i64).contains(&index).then_some(index as usize)
+}
+
+/// Print or change the command history.
+///
+/// # Parameters
+/// - `args`: nothing to print every entry; `N` to print the last `N`;
+///   `-c` to clear the history; `-d offset` or `-d start-end` to delete
+///   entries (negative offsets count from the end); `-s pattern` to print
+///   the entries containing `pattern`, which may use `*`, `?` and `[...]`;
+///   `-a`, `-w` or `-r`, optionally followed by a file name (`$HISTFILE`
+///   by default), to append the new entries to the file, write the whole
+///   history to it or read the file into the history.
+/// - `state`: shell state holding the history and its settings.
+/// - `stdout`: stream the history is written to.
+/// - `stderr`: stream errors are written to.
+///
+/// # Returns
+/// - `0` on success, `1` on error, `2` for an invalid option.
+pub fn history(
+    args: &[String],
+    state: &mut ShellState,
+    stdout: &mut dyn Write,
+    stderr: &mut dyn Write,
+) -> i32 {
+    let le = state.hist.len().to_string().len();
+    let numbered = state.hist.entries().iter().enumerate();
+    let Some(option) = args.first() else {
+        return print_entries(numbered, le, stdout);
+    };
+
+    match option.as_str() {
+        "-c" => {
+            state.hist.clear();
+            0
+        }
+        "-d" => {
+            let Some(offset) = args.get(1) else {
+                write_error(stderr, "history: -d: option requires an argument");
+                return 2;
+            };
+            let len = state.hist.len();
+            let range = match offset
+                .split_once('-')
+                .filter(|(start, _)| !start.is_empty())
+            {
+                Some((start, end)) => position(start, len).zip(position(end, len)),
+                None => position(offset, len).map(|index| (index, index)),
+            };
+            match range {
+                Some((start, end)) if start <= end => {
+                    for _ in start..=end {
+                        state.hist.remove(start);
+                    }
+                    0
+                }
+                _ => {
+                    let message = format!("history: {}: history position out of range", offset);
+                    write_error(stderr, &message);
+                    1
+                }
+            }
+        }
+        "-s" => {
+            let Some(pattern) = args.get(1) else {
+                write_error(stderr, "history: -s: option requires an argument");
+                return 2;
+            };
+            let pattern = format!("*{}*", pattern);
+            let matching = numbered.filter(|(_, entry)| pattern_matches(&pattern, entry));
+            print_entries(matching, le, stdout)
+        }
+        "-a" | "-w" | "-r" => {
+            let path = match args.get(1) {
+                Some(path) => state.current_dir.join(path),
+                None => match history_file(state) {
+                    Some(path) => state.current_dir.join(path),
+                    None => {
+                        write_error(stderr, "history: HISTFILE is not set");
+                        return 1;
+                    }
+                },
+            };
+            let file_size = parse_size(state.get_var("HISTFILESIZE"));
+            let res = match option.as_str() {
+                "-a" => append_history_file(&path, state.hist.unsaved(), file_size),
+                "-w" => write_history_file(&path, state.hist.entries(), file_size),
+                _ => read_history_file(&path).map(|entries| state.hist.extend(entries)),
+            };
+            match res {
+                Ok(()) => {
+                    // What was typed before `-r` is still to be written
+                    if option != "-r" {
+                        state.hist.mark_saved();
+                    }
+                    0
+                }
+                Err(err) => {
+                    write_error(stderr, &format!("history: {}: {}", path.display(), err));
+                    1
+                }
+            }
+        }
+        arg if arg.starts_with('-') && arg.len() > 1 && arg.parse::<i64>().is_err() => {
+            write_error(stderr, &format!("history: {}: invalid option", arg));
+            write_error(
+                stderr,
+                "history: usage: history [-c] [-d offset] [-s pattern] [n] or history -awr [filename]",
+            );
+            2
+        }
+        count => match count.parse::<usize>() {
+            Ok(count) => {
+                let skipped = state.hist.len().saturating_sub(count);
+                print_entries(numbered.skip(skipped), le, stdout)
+            }
+            Err(_) => {
+                write_error(
+                    stderr,
+                    &format!("history: {}: numeric argument required", count),
+                );
+                1
+            }
+        },
+    }
+}
+
+/// `history` builtin, see [`history`].
+pub struct HistoryBuiltin;
+
+impl Builtin for HistoryBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        history(args, state, io.stdout, io.stderr)
+    }
+
+    fn flags(&self) -> &str {
+        "cdsawr"
+    }
+
+    /// `-d` takes the number of an entry; the file names of `-a`, `-w` and
+    /// `-r` are completed as usual.
+    fn complete(
+        &self,
+        args: &[String],
+        word: &str,
+        state: &mut ShellState,
+    ) -> Option<Vec<Candidate>> {
+        if args.last().map(String::as_str) != Some("-d") {
+            return None;
+        }
+        let numbers = (1..=state.hist.len())
+            .map(|n| n.to_string())
+            .filter(|n| n.starts_with(word))
+            .map(|n| Candidate {
+                text: n.clone(),
+                display: n,
+            })
+            .collect();
+        Some(numbers)
+    }
+}
@@ -0,0 +1,64 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+/// Set when `SIGINT` arrives, until the REPL or the script runner sees it.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Process group of the job holding the terminal, `0` while the shell does.
+static FOREGROUND: AtomicI32 = AtomicI32::new(0);
+
+/// Handler of `SIGINT`: raise the flag and pass the signal on to the job
+/// holding the terminal, which does not get it when it was sent to the
+/// shell alone (`kill -INT`).
+extern "C" fn on_interrupt(_signal: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+    let pgid = FOREGROUND.load(Ordering::SeqCst);
+    if pgid > 0 {
+        unsafe { libc::kill(-pgid, libc::SIGINT) };
+    }
+}
+
+/// Catch `SIGINT` (`Ctrl+C`) so it interrupts the running command instead
+/// of killing the shell. System calls are not restarted after it, so a
+/// builtin blocked reading stdin gets the chance to stop.
+///
+/// # Returns
+/// - `Ok(())` once the handler is installed, or the error of `sigaction`.
+pub fn catch_interrupts() -> io::Result<()> {
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        libc::sigemptyset(&mut action.sa_mask);
+        if libc::sigaction(libc::SIGINT, &action, std::ptr::null_mut()) == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Return `true` when `SIGINT` arrived and was not handled yet. Long-running
+/// builtins poll it to stop early with status `130`, and lists and loops
+/// stop running commands.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Raise the flag as `SIGINT` does, for a foreground child killed by it:
+/// the shell then abandons the rest of the line too.
+pub fn interrupt() {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Clear the flag.
+///
+/// # Returns
+/// - `true` if `SIGINT` arrived since the last call.
+pub fn take_interrupt() -> bool {
+    INTERRUPTED.swap(false, Ordering::SeqCst)
+}
+
+/// Record the process group of the job given the terminal, which `SIGINT`
+/// is passed on to, or `0` when the shell takes the terminal back.
+pub fn set_foreground(pgid: libc::pid_t) {
+    FOREGROUND.store(pgid, Ordering::SeqCst);
+}
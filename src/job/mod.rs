@@ -0,0 +1,651 @@
+use crate::*;
+use std::cmp::Reverse;
+use std::ffi::CStr;
+use std::io;
+use std::io::ErrorKind;
+use std::io::Write;
+
+/// What the processes of a job are doing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobState {
+    Running, // Running in the background, or not waited for yet
+    Stopped, // Suspended, by `Ctrl+Z` or a signal such as `SIGSTOP`
+    Done,    // Every process ended, `status` tells how
+}
+
+/// A pipeline or list run as a job: started in the background with `&`, or
+/// stopped with `Ctrl+Z` while it held the terminal.
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub id: usize,              // Number of the job, as in `%1`
+    pub pgid: libc::pid_t,      // Process group of the job, signalled as a whole
+    pub pids: Vec<libc::pid_t>, // Processes of the job not reaped yet
+    pub last_pid: libc::pid_t,  // Process whose status is the status of the job
+    pub command: String,        // Text of the command, as `jobs` shows it
+    pub state: JobState,        // What its processes are doing
+    pub status: libc::c_int,    // Wait status of the last process once it ended
+    pub notified: bool,         // The last change of state was reported
+}
+
+impl Job {
+    /// Create a running job of the processes `pids`, the last one giving
+    /// its status. Its number is given when it enters the job table.
+    pub fn new(pgid: libc::pid_t, pids: Vec<libc::pid_t>, command: String) -> Self {
+        Self {
+            id: 0,
+            pgid,
+            last_pid: pids.last().copied().unwrap_or(pgid),
+            pids,
+            command,
+            state: JobState::Running,
+            status: 0,
+            notified: true,
+        }
+    }
+
+    /// Record the wait status `status` reported for the process `pid`.
+    fn record(&mut self, pid: libc::pid_t, status: libc::c_int) {
+        if libc::WIFSTOPPED(status) {
+            self.state = JobState::Stopped;
+        } else if libc::WIFCONTINUED(status) {
+            self.state = JobState::Running;
+        } else {
+            self.pids.retain(|&p| p != pid);
+            if pid == self.last_pid {
+                self.status = status;
+            }
+            if self.pids.is_empty() {
+                self.state = JobState::Done;
+            }
+        }
+    }
+
+    /// Return the exit code of an ended job, `128 + signal` when its last
+    /// process was killed.
+    fn exit_code(&self) -> i32 {
+        if libc::WIFSIGNALED(self.status) {
+            128 + libc::WTERMSIG(self.status)
+        } else {
+            libc::WEXITSTATUS(self.status)
+        }
+    }
+
+    /// Describe the state of the job as `jobs` shows it: `Running`,
+    /// `Stopped`, `Done`, `Exit N` or the signal that killed it.
+    fn describe(&self) -> String {
+        match self.state {
+            JobState::Running => "Running".to_string(),
+            JobState::Stopped => "Stopped".to_string(),
+            JobState::Done if libc::WIFSIGNALED(self.status) => {
+                let signal = libc::WTERMSIG(self.status);
+                let description = unsafe { CStr::from_ptr(libc::strsignal(signal)) };
+                let core = if libc::WCOREDUMP(self.status) {
+                    " (core dumped)"
+                } else {
+                    ""
+                };
+                format!("{}{}", description.to_string_lossy(), core)
+            }
+            JobState::Done => match self.exit_code() {
+                0 => "Done".to_string(),
+                code => format!("Exit {}", code),
+            },
+        }
+    }
+}
+
+/// Turn on job control for an interactive shell: wait until it runs in the
+/// foreground of the terminal, move it to a process group of its own and
+/// ignore the signals sent by the terminal to stop it. Does nothing when
+/// stdin is not a terminal.
+pub fn init_job_control(state: &mut ShellState) {
+    unsafe {
+        if libc::isatty(libc::STDIN_FILENO) == 0 {
+            return;
+        }
+        // Started in the background: stop until brought to the foreground
+        while libc::tcgetpgrp(libc::STDIN_FILENO) != libc::getpgrp() {
+            libc::kill(-libc::getpgrp(), libc::SIGTTIN);
+        }
+        libc::signal(libc::SIGTSTP, libc::SIG_IGN);
+        libc::signal(libc::SIGTTIN, libc::SIG_IGN);
+        libc::signal(libc::SIGTTOU, libc::SIG_IGN);
+        let pid = libc::getpid();
+        libc::setpgid(pid, pid);
+        libc::tcsetpgrp(libc::STDIN_FILENO, pid);
+
+        let mut modes = std::mem::MaybeUninit::uninit();
+        if libc::tcgetattr(libc::STDIN_FILENO, modes.as_mut_ptr()) == 0 {
+            state.terminal_modes = Some(modes.assume_init());
+        }
+    }
+    state.job_control = true;
+}
+
+/// Add `job` to the job table, giving it the next free number unless it
+/// already has one, as a job brought back by `fg` does.
+///
+/// # Returns
+/// - the number of the job.
+pub fn add_job(mut job: Job, state: &mut ShellState) -> usize {
+    if job.id == 0 {
+        job.id = state.jobs.iter().map(|job| job.id).max().unwrap_or(0) + 1;
+    }
+    let id = job.id;
+    let at = state.jobs.partition_point(|other| other.id < id);
+    state.jobs.insert(at, job);
+    id
+}
+
+/// Give the terminal to `job` and wait for its processes, until they all
+/// end or the job stops. The shell then takes the terminal back, with the
+/// modes it had before. A stopped job is reported and kept in the job
+/// table for `fg` and `bg`.
+///
+/// # Returns
+/// - the exit code of the last process, or `128 + signal` when the job
+///   was stopped or killed.
+pub fn wait_foreground(mut job: Job, state: &mut ShellState) -> i32 {
+    unsafe { libc::tcsetpgrp(libc::STDIN_FILENO, job.pgid) };
+    set_foreground(job.pgid);
+    let mut stop_signal = 0;
+    for pid in job.pids.clone() {
+        let mut status = 0;
+        while unsafe { libc::waitpid(pid, &mut status, libc::WUNTRACED) } == -1 {
+            if io::Error::last_os_error().kind() != ErrorKind::Interrupted {
+                // Not a child anymore: count it as a success
+                status = 0;
+                break;
+            }
+        }
+        job.record(pid, status);
+        if libc::WIFSTOPPED(status) {
+            stop_signal = libc::WSTOPSIG(status);
+        }
+    }
+    set_foreground(0);
+    unsafe {
+        libc::tcsetpgrp(libc::STDIN_FILENO, libc::getpgrp());
+        if let Some(modes) = &state.terminal_modes {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSADRAIN, modes);
+        }
+    }
+
+    if job.state == JobState::Stopped {
+        let id = add_job(job, state);
+        eprintln!();
+        if let Some(job) = state.jobs.iter().find(|job| job.id == id) {
+            eprintln!("{}", format_job(job, state, false));
+        }
+        return 128 + stop_signal;
+    }
+    report_status(job.status)
+}
+
+/// Reap the processes of the jobs that ended, stopped or went on since the
+/// last check, without waiting.
+pub fn update_jobs(state: &mut ShellState) {
+    let flags = libc::WNOHANG | libc::WUNTRACED | libc::WCONTINUED;
+    for job in &mut state.jobs {
+        for pid in job.pids.clone() {
+            let mut status = 0;
+            if unsafe { libc::waitpid(pid, &mut status, flags) } <= 0 {
+                continue;
+            }
+            let previous = job.state;
+            job.record(pid, status);
+            if job.state != previous {
+                job.notified = false;
+            }
+        }
+    }
+}
+
+/// Report the jobs that ended or stopped since the last prompt, as in
+/// `[1]+  Done                    sleep 5`, and forget the ended ones.
+pub fn notify_jobs(state: &mut ShellState) {
+    update_jobs(state);
+    for job in state.jobs.iter().filter(|job| !job.notified) {
+        eprintln!("{}", format_job(job, state, false));
+    }
+    for job in &mut state.jobs {
+        job.notified = true;
+    }
+    state.jobs.retain(|job| job.state != JobState::Done);
+}
+
+/// Return the numbers of the jobs from the current one, `%+`, to the
+/// oldest: the stopped jobs come first, then the most recent ones.
+fn ranked_jobs(state: &ShellState) -> Vec<usize> {
+    let mut jobs = state.jobs.iter().collect::<Vec<_>>();
+    jobs.sort_by_key(|job| (job.state != JobState::Stopped, Reverse(job.id)));
+    jobs.into_iter().map(|job| job.id).collect()
+}
+
+/// Write a line of `jobs`: `[1]+  Running                 sleep 5 &`, with
+/// the process id after the marker when `long` is set.
+fn format_job(job: &Job, state: &ShellState, long: bool) -> String {
+    let ranked = ranked_jobs(state);
+    let marker = match ranked.iter().position(|&id| id == job.id) {
+        Some(0) => '+',
+        Some(1) => '-',
+        _ => ' ',
+    };
+    let background = if job.state == JobState::Running {
+        " &"
+    } else {
+        ""
+    };
+    let pid = if long {
+        format!("{} ", job.pgid)
+    } else {
+        " ".to_string()
+    };
+    format!(
+        "[{}]{} {}{:<24}{}{}",
+        job.id,
+        marker,
+        pid,
+        job.describe(),
+        job.command,
+        background
+    )
+}
+
+/// Find the job named by `spec`: `%n` by number, `%+`, `%%` or no spec for
+/// the current job, `%-` for the previous one, or `%text` for the job
+/// whose command starts with `text`.
+///
+/// # Returns
+/// - the index of the job in the table, or an error message starting with
+///   `name`.
+fn find_job(spec: Option<&str>, state: &ShellState, name: &str) -> Result<usize, String> {
+    let ranked = ranked_jobs(state);
+    let (id, text) = match spec {
+        None | Some("%" | "%%" | "%+") => (ranked.first().copied(), "current"),
+        Some("%-") => (ranked.get(1).copied(), "previous"),
+        Some(spec) => {
+            let text = spec.strip_prefix('%').unwrap_or(spec);
+            let id = match text.parse::<usize>() {
+                Ok(id) => Some(id),
+                Err(_) => state
+                    .jobs
+                    .iter()
+                    .find(|job| job.command.starts_with(text))
+                    .map(|job| job.id),
+            };
+            (id, spec)
+        }
+    };
+    id.and_then(|id| state.jobs.iter().position(|job| job.id == id))
+        .ok_or_else(|| format!("{}: {}: no such job", name, text))
+}
+
+/// List the jobs with their state, then forget the ones that ended.
+///
+/// # Parameters
+/// - `args`: `-l` to add process ids, `-p` to only print them, then the
+///   jobs to list, all by default.
+/// - `state`: shell state holding the jobs.
+/// - `stdout`: stream the jobs are printed to.
+/// - `stderr`: stream errors are written to.
+///
+/// # Returns
+/// - `0` on success, `1` when a job does not exist, `2` on an invalid
+///   option.
+pub fn jobs(
+    args: &[String],
+    state: &mut ShellState,
+    stdout: &mut dyn Write,
+    stderr: &mut dyn Write,
+) -> i32 {
+    let mut long = false;
+    let mut pids_only = false;
+    let mut specs = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "-l" => long = true,
+            "-p" => pids_only = true,
+            _ if arg.starts_with('-') => {
+                write_error(stderr, &format!("jobs: {}: invalid option", arg));
+                return 2;
+            }
+            _ => specs.push(arg.as_str()),
+        }
+    }
+
+    update_jobs(state);
+    let mut status = 0;
+    let mut listed = Vec::new();
+    if specs.is_empty() {
+        listed.extend(0..state.jobs.len());
+    }
+    for spec in specs {
+        match find_job(Some(spec), state, "jobs") {
+            Ok(index) => listed.push(index),
+            Err(err) => {
+                write_error(stderr, &err);
+                status = 1;
+            }
+        }
+    }
+    for index in listed {
+        let job = &state.jobs[index];
+        let line = if pids_only {
+            job.pgid.to_string()
+        } else {
+            format_job(job, state, long)
+        };
+        if writeln!(stdout, "{}", line).is_err() {
+            return 1;
+        }
+        state.jobs[index].notified = true;
+    }
+    state
+        .jobs
+        .retain(|job| job.state != JobState::Done || !job.notified);
+    status
+}
+
+/// Bring a job to the foreground, continuing it if it was stopped, and wait
+/// for it.
+///
+/// # Parameters
+/// - `args`: the job, the current one by default.
+/// - `state`: shell state holding the jobs.
+/// - `stdout`: stream the command of the job is printed to.
+/// - `stderr`: stream errors are written to.
+///
+/// # Returns
+/// - the exit status of the job, `1` without job control or when the job
+///   does not exist.
+pub fn fg(
+    args: &[String],
+    state: &mut ShellState,
+    stdout: &mut dyn Write,
+    stderr: &mut dyn Write,
+) -> i32 {
+    if !state.job_control {
+        write_error(stderr, "fg: no job control");
+        return 1;
+    }
+    update_jobs(state);
+    let index = match find_job(args.first().map(String::as_str), state, "fg") {
+        Ok(index) => index,
+        Err(err) => {
+            write_error(stderr, &err);
+            return 1;
+        }
+    };
+    let mut job = state.jobs.remove(index);
+    let _ = writeln!(stdout, "{}", job.command);
+    let _ = stdout.flush();
+    if job.state == JobState::Done {
+        return job.exit_code();
+    }
+    unsafe { libc::kill(-job.pgid, libc::SIGCONT) };
+    job.state = JobState::Running;
+    wait_foreground(job, state)
+}
+
+/// Continue stopped jobs in the background.
+///
+/// # Parameters
+/// - `args`: the jobs, the current one by default.
+/// - `state`: shell state holding the jobs.
+/// - `stdout`: stream the commands of the jobs are printed to.
+/// - `stderr`: stream errors are written to.
+///
+/// # Returns
+/// - `0` on success, `1` without job control or when a job does not exist.
+pub fn bg(
+    args: &[String],
+    state: &mut ShellState,
+    stdout: &mut dyn Write,
+    stderr: &mut dyn Write,
+) -> i32 {
+    if !state.job_control {
+        write_error(stderr, "bg: no job control");
+        return 1;
+    }
+    update_jobs(state);
+    let specs = if args.is_empty() {
+        vec![None]
+    } else {
+        args.iter().map(|arg| Some(arg.as_str())).collect()
+    };
+    let mut status = 0;
+    for spec in specs {
+        let index = match find_job(spec, state, "bg") {
+            Ok(index) => index,
+            Err(err) => {
+                write_error(stderr, &err);
+                status = 1;
+                continue;
+            }
+        };
+        let job = &mut state.jobs[index];
+        if job.state != JobState::Stopped {
+            let message = format!("bg: job {} already in background", job.id);
+            write_error(stderr, &message);
+            continue;
+        }
+        unsafe { libc::kill(-job.pgid, libc::SIGCONT) };
+        job.state = JobState::Running;
+        let _ = writeln!(stdout, "[{}] {} &", job.id, job.command);
+    }
+    status
+}
+
+/// Block until every process of `job` ends, or until `Ctrl+C`.
+///
+/// # Returns
+/// - `false` when interrupted, the job being left as it is.
+fn wait_job(job: &mut Job) -> bool {
+    for pid in job.pids.clone() {
+        let mut status = 0;
+        while unsafe { libc::waitpid(pid, &mut status, 0) } == -1 {
+            if interrupted() {
+                return false;
+            }
+            if io::Error::last_os_error().kind() != ErrorKind::Interrupted {
+                status = 0;
+                break;
+            }
+        }
+        job.record(pid, status);
+    }
+    true
+}
+
+/// Wait for jobs to end, then forget them.
+///
+/// # Parameters
+/// - `args`: jobs (`%n`) or process ids to wait for, every running job by
+///   default: stopped jobs would never end, so they are left alone.
+/// - `state`: shell state holding the jobs.
+/// - `stderr`: stream errors are written to.
+///
+/// # Returns
+/// - the exit status of the last job waited for, `0` without arguments,
+///   `127` when a job or process is not known, or `130` when interrupted.
+pub fn wait(args: &[String], state: &mut ShellState, stderr: &mut dyn Write) -> i32 {
+    if args.is_empty() {
+        update_jobs(state);
+        for job in &mut state.jobs {
+            if job.state == JobState::Running && !wait_job(job) {
+                return 130;
+            }
+        }
+        state.jobs.retain(|job| job.state != JobState::Done);
+        return 0;
+    }
+
+    let mut status = 0;
+    for arg in args {
+        let index = if arg.starts_with('%') {
+            find_job(Some(arg), state, "wait")
+        } else {
+            match arg.parse::<libc::pid_t>() {
+                Ok(pid) => state
+                    .jobs
+                    .iter()
+                    .position(|job| job.pgid == pid || job.pids.contains(&pid))
+                    .ok_or_else(|| format!("wait: pid {} is not a child of this shell", pid)),
+                Err(_) => Err(format!("wait: `{}': not a pid or valid job spec", arg)),
+            }
+        };
+        match index {
+            Ok(index) => {
+                if !wait_job(&mut state.jobs[index]) {
+                    return 130;
+                }
+                status = state.jobs.remove(index).exit_code();
+            }
+            Err(err) => {
+                write_error(stderr, &err);
+                status = 127;
+            }
+        }
+    }
+    status
+}
+
+/// Remove jobs from the job table, so the shell no longer reports nor
+/// waits for them.
+///
+/// # Parameters
+/// - `args`: `-a` for every job, or the jobs, the current one by default.
+/// - `state`: shell state holding the jobs.
+/// - `stderr`: stream errors are written to.
+///
+/// # Returns
+/// - `0` on success, `1` when a job does not exist.
+pub fn disown(args: &[String], state: &mut ShellState, stderr: &mut dyn Write) -> i32 {
+    if args.first().map(String::as_str) == Some("-a") {
+        state.jobs.clear();
+        return 0;
+    }
+    let specs = if args.is_empty() {
+        vec![None]
+    } else {
+        args.iter().map(|arg| Some(arg.as_str())).collect()
+    };
+    let mut status = 0;
+    for spec in specs {
+        match find_job(spec, state, "disown") {
+            Ok(index) => {
+                state.jobs.remove(index);
+            }
+            Err(err) => {
+                write_error(stderr, &err);
+                status = 1;
+            }
+        }
+    }
+    status
+}
+
+/// Complete job specs, `%1` and so on, for the builtins taking jobs.
+fn complete_jobs(word: &str, state: &ShellState) -> Vec<Candidate> {
+    state
+        .jobs
+        .iter()
+        .map(|job| format!("%{}", job.id))
+        .filter(|spec| spec.starts_with(word))
+        .map(|spec| Candidate {
+            text: spec.clone(),
+            display: spec,
+        })
+        .collect()
+}
+
+/// `jobs` builtin, see [`jobs`].
+pub struct JobsBuiltin;
+
+impl Builtin for JobsBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        jobs(args, state, io.stdout, io.stderr)
+    }
+
+    fn flags(&self) -> &str {
+        "lp"
+    }
+}
+
+/// `fg` builtin, see [`fg`].
+pub struct FgBuiltin;
+
+impl Builtin for FgBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        fg(args, state, io.stdout, io.stderr)
+    }
+
+    /// Job specs, `%1` and so on.
+    fn complete(
+        &self,
+        _args: &[String],
+        word: &str,
+        state: &mut ShellState,
+    ) -> Option<Vec<Candidate>> {
+        Some(complete_jobs(word, state))
+    }
+}
+
+/// `bg` builtin, see [`bg`].
+pub struct BgBuiltin;
+
+impl Builtin for BgBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        bg(args, state, io.stdout, io.stderr)
+    }
+
+    fn complete(
+        &self,
+        _args: &[String],
+        word: &str,
+        state: &mut ShellState,
+    ) -> Option<Vec<Candidate>> {
+        Some(complete_jobs(word, state))
+    }
+}
+
+/// `wait` builtin, see [`wait`].
+pub struct WaitBuiltin;
+
+impl Builtin for WaitBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        wait(args, state, io.stderr)
+    }
+
+    fn complete(
+        &self,
+        _args: &[String],
+        word: &str,
+        state: &mut ShellState,
+    ) -> Option<Vec<Candidate>> {
+        Some(complete_jobs(word, state))
+    }
+}
+
+/// `disown` builtin, see [`disown`].
+pub struct DisownBuiltin;
+
+impl Builtin for DisownBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        disown(args, state, io.stderr)
+    }
+
+    fn flags(&self) -> &str {
+        "a"
+    }
+
+    fn complete(
+        &self,
+        _args: &[String],
+        word: &str,
+        state: &mut ShellState,
+    ) -> Option<Vec<Candidate>> {
+        Some(complete_jobs(word, state))
+    }
+}
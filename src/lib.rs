@@ -1,182 +1,88 @@
+pub mod alias;
+pub mod builtin;
 pub mod cat;
 pub mod cd;
+pub mod clear;
+pub mod command_type;
+pub mod complete;
 pub mod cp;
 pub mod echo;
+pub mod editor;
+pub mod env;
+pub mod exec;
+pub mod exit;
+pub mod expand;
+pub mod export;
+pub mod function;
+pub mod glob;
 pub mod history;
+pub mod interrupt;
+pub mod job;
+pub mod loop_control;
 pub mod ls;
 pub mod mkdir;
 pub mod mv;
+pub mod parser;
+pub mod prompt;
 pub mod pwd;
+pub mod repl;
 pub mod rm;
+pub mod set;
+pub mod source;
+pub mod state;
+pub mod trap;
+pub mod unset;
+pub use alias::*;
+pub use builtin::*;
 pub use cat::*;
 pub use cd::*;
+pub use clear::*;
+pub use command_type::*;
+pub use complete::*;
 pub use cp::*;
 pub use echo::*;
+pub use editor::*;
+pub use env::*;
+pub use exec::*;
+pub use exit::*;
+pub use expand::*;
+pub use export::*;
+pub use function::*;
+pub use glob::*;
 pub use history::*;
+pub use interrupt::*;
+pub use job::*;
+pub use loop_control::*;
 pub use ls::*;
 pub use mkdir::*;
 pub use mv::*;
+pub use parser::*;
+pub use prompt::*;
 pub use pwd::*;
+pub use repl::*;
 pub use rm::*;
+pub use set::*;
+pub use source::*;
+pub use state::*;
+pub use trap::*;
+pub use unset::*;
 
+use std::io;
+use std::io::Write;
 
-#[derive(Debug, PartialEq, Clone)]
-pub struct Command {
-    pub name: String,      // The command name, e.g., "echo"
-    pub args: Vec<String>, // List of arguments
-}
-
-impl Command {
-    /// Add a parsed token to the command. If the command name is empty it
-    /// becomes the `name`, otherwise the token is appended to `args`.
-    ///
-    /// # Parameters
-    /// - `word`: token to add to the command structure.
-    pub fn add_string(&mut self, word: &String) {
-        if word.is_empty() {
-            return;
-        }
-        if self.name.is_empty() {
-            self.name = word.clone();
-        } else {
-            self.args.push(word.clone());
-        }
-    }
-
-    /// Similar to `add_string` but used when the token comes from a quoted
-    /// section; always pushes the token as-is to `name` or `args`.
-    ///
-    /// # Parameters
-    /// - `word`: token extracted from a quoted section.
-    pub fn add_string_whatever(&mut self, word: &String) {
-        if self.name.is_empty() {
-            self.name = word.clone();
-        } else {
-            self.args.push(word.clone());
-        }
-    }
-}
-
-pub trait CostumSplit {
-    /// Split the string into a `Command` and indicate if quotes are left open.
-    ///
-    /// # Returns
-    /// - `(Command, bool)` where `Command` holds parsed `name` and `args`, and
-    ///   `bool` is `true` when there is an unterminated quote or open backslash.
-    fn custom_split(&self) -> (Command, bool);
-}
-
-impl CostumSplit for String {
-    /// Parse the string into a `Command` splitting on whitespace while honoring
-    /// single and double quotes and backslash escapes.
-    ///
-    /// # Returns
-    /// - `(Command, bool)` where the `Command` has `name` and `args`, and the
-    ///   `bool` is `true` if there is an unterminated quote or open backslash.
-    fn custom_split(&self) -> (Command, bool) {
-        let mut command = Command {
-            name: String::new(),
-            args: Vec::new(),
-        };
-        let mut word = String::new();
-        let mut state = State::Normal;
-        let mut open_backslash = false;
-
-        #[derive(Debug, PartialEq)]
-        enum State {
-            Normal,
-            DoubleQuote,
-            SingleQuote,
-        }
 
-        let chs = self.split("\n").collect::<Vec<_>>();
-        for (i, line) in chs.iter().enumerate() {
-            if state != State::Normal && !open_backslash {
-                word.push('\n');
-            }
-            if open_backslash && i != chs.len() - 1 {
-                open_backslash = false;
-            }
-            let mut chars = line.chars().peekable();
-            while let Some(ch) = chars.next() {
-                match state {
-                    State::Normal => {
-                        if ch == '\\' && !open_backslash {
-                            open_backslash = true;
-                        } else if ch.is_whitespace() && !open_backslash {
-                            command.add_string(&word);
-
-                            // let le = command.args.len();
-                            // if le > 0
-                            //     && command.args[le - 1] != " ".to_string()
-                            // {
-                            //     command.add_string(&" ".to_string());
-                            // }
-
-                            word.clear();
-                        } else if ch == '"' && !open_backslash {
-                            state = State::DoubleQuote;
-                        } else if ch == '\'' && !open_backslash {
-                            state = State::SingleQuote;
-                        } else {
-                            if open_backslash {
-                                word.push(ch);
-                                open_backslash = false;
-                            } else {
-                                word.push(ch);
-                            }
-                        }
-                    }
-                    State::DoubleQuote => {
-                        if ch == '"' && !open_backslash {
-                            state = State::Normal;
-                            if let Some(ch2) = chars.peek() {
-                                if ch2.is_whitespace() {
-                                    command.add_string_whatever(&word);
-                                    word.clear();
-                                    chars.next();
-                                }
-                            }
-                        } else if ch == '\\' && !open_backslash {
-                            open_backslash = true;
-                        } else {
-                            if open_backslash {
-                                if ['"', '\\', '`', '$'].contains(&ch) {
-                                    word.push(ch);
-                                } else {
-                                    word.push('\\');
-                                    word.push(ch);
-                                }
-                                open_backslash = false;
-                            } else {
-                                word.push(ch);
-                            }
-                        }
-                    }
-                    State::SingleQuote => {
-                        if ch == '\'' {
-                            state = State::Normal;
-                            if let Some(ch2) = chars.peek() {
-                                if ch2.is_whitespace() {
-                                    command.add_string_whatever(&word);
-                                    word.clear();
-                                    chars.next();
-                                }
-                            }
-                        } else {
-                            word.push(ch);
-                        }
-                    }
-                }
-            }
-        }
-
-        if !word.is_empty() {
-            command.add_string(&word);
-        }
-
-        let open = matches!(state, State::DoubleQuote | State::SingleQuote) || open_backslash;
-        (command, open)
+/// Quote `word` so the shell reads it back as a single literal word, as
+/// printed by `set` and `export`.
+///
+/// # Returns
+/// - `word` unchanged when it only holds safe characters, otherwise `word`
+///   between single quotes with every `'` written as `'\''`.
+pub fn quote(word: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "_./:=@%+,-".contains(c);
+    if !word.is_empty() && word.chars().all(safe) {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
     }
 }
 
@@ -185,5 +91,14 @@ impl CostumSplit for String {
 /// # Parameters
 /// - `message`: error message to print.
 pub fn print_error(message: &str) {
-    eprintln!("\x1b[31m {}\x1b[0m", message)
+    write_error(&mut io::stderr(), message)
+}
+
+/// Write an error message with red coloring to the given error stream.
+///
+/// # Parameters
+/// - `stderr`: stream the message is written to.
+/// - `message`: error message to write.
+pub fn write_error(stderr: &mut dyn Write, message: &str) {
+    let _ = writeln!(stderr, "\x1b[31m {}\x1b[0m", message);
 }
@@ -0,0 +1,78 @@
+use crate::{Builtin, Flow, ShellState, Streams, write_error};
+use std::io::Write;
+
+/// Ask the enclosing loops to jump, for `break` and `continue`.
+///
+/// # Parameters
+/// - `name`: name of the builtin, for error messages.
+/// - `args`: optional number of loops to leave, `1` by default; a number
+///   above the depth of the loops leaves all of them.
+/// - `flow`: builds the jump from that number.
+/// - `state`: shell state the jump is recorded in.
+/// - `stderr`: stream errors are written to.
+///
+/// # Returns
+/// - `0`, even outside of a loop where nothing happens, or `1` when the
+///   number is invalid, which leaves every enclosing loop as bash does.
+fn jump(
+    name: &str,
+    args: &[String],
+    flow: fn(usize) -> Flow,
+    state: &mut ShellState,
+    stderr: &mut dyn Write,
+) -> i32 {
+    if args.len() > 1 {
+        write_error(stderr, &format!("{}: too many arguments", name));
+        return 1;
+    }
+    let count = match args.first().map(|arg| arg.parse::<usize>()) {
+        None => 1,
+        Some(Ok(count)) if count > 0 => count,
+        Some(_) => {
+            write_error(stderr, &format!("{}: Illegal number: {}", name, args[0]));
+            if state.loop_depth > 0 {
+                state.flow = Some(Flow::Break(state.loop_depth));
+            }
+            return 1;
+        }
+    };
+    if state.loop_depth == 0 {
+        let message = format!(
+            "{}: only meaningful in a `for', `while', or `until' loop",
+            name
+        );
+        write_error(stderr, &message);
+        return 0;
+    }
+    state.flow = Some(flow(count.min(state.loop_depth)));
+    0
+}
+
+/// Leave the innermost loop, or the `n` innermost loops with `break n`.
+pub fn break_loop(args: &[String], state: &mut ShellState, stderr: &mut dyn Write) -> i32 {
+    jump("break", args, Flow::Break, state, stderr)
+}
+
+/// Go on with the next iteration of the innermost loop, or of the `n`-th
+/// enclosing loop with `continue n`.
+pub fn continue_loop(args: &[String], state: &mut ShellState, stderr: &mut dyn Write) -> i32 {
+    jump("continue", args, Flow::Continue, state, stderr)
+}
+
+/// `break` builtin, see [`break_loop`].
+pub struct BreakBuiltin;
+
+impl Builtin for BreakBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        break_loop(args, state, io.stderr)
+    }
+}
+
+/// `continue` builtin, see [`continue_loop`].
+pub struct ContinueBuiltin;
+
+impl Builtin for ContinueBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        continue_loop(args, state, io.stderr)
+    }
+}
@@ -1,6 +1,7 @@
 use chrono::Datelike;
 use chrono::{DateTime, Local};
 use chrono_tz::Tz;
+use std::cmp::Ordering;
 use std::fs;
 use std::fs::Metadata;
 use std::fs::Permissions;
@@ -11,6 +12,60 @@ use std::time::SystemTime;
 use users::*;
 
 // helpers
+/// Characters that make `ls` quote a file name, as the shell would give
+/// them a special meaning.
+pub const UNSAFE_CHARACTERS: &str = "*?[]$!\"\\;&|<> ()`~#=";
+
+/// Compute the grid `ls` lays names out in: a single row when they all fit
+/// in the terminal, otherwise as many columns as fit, filled column by
+/// column.
+///
+/// # Parameters
+/// - `count`: number of names.
+/// - `max_width`: width of the longest name.
+/// - `term_width`: width of the terminal.
+///
+/// # Returns
+/// - the number of columns, of rows, and the width of a column, padding
+///   included.
+pub fn grid_size(count: usize, max_width: usize, term_width: usize) -> (usize, usize, usize) {
+    let col_width = max_width + 2; // Add padding for spacing
+    let total_width = (count * col_width).saturating_sub(2); // Total width without last padding
+
+    if total_width <= term_width {
+        // Single row if all files fit
+        (count, 1, col_width)
+    } else {
+        // Multiple columns based on terminal width
+        let num_cols = (term_width / col_width).max(1);
+        (num_cols, count.div_ceil(num_cols), col_width)
+    }
+}
+
+/// Lay `names` out in the grid `ls` uses, see [`grid_size`].
+///
+/// # Returns
+/// - the rows of the grid, each ending with a newline.
+pub fn format_grid(names: &[String], term_width: usize) -> String {
+    let max_width = names.iter().map(|n| n.chars().count()).max().unwrap_or(0);
+    let (num_cols, num_rows, col_width) = grid_size(names.len(), max_width, term_width);
+    let mut output = String::new();
+    for row in 0..num_rows {
+        let cells = (0..num_cols)
+            .filter_map(|col| names.get(col * num_rows + row))
+            .collect::<Vec<_>>();
+        for (i, name) in cells.iter().enumerate() {
+            if i + 1 == cells.len() {
+                output.push_str(name);
+            } else {
+                output.push_str(&format!("{:width$}", name, width = col_width));
+            }
+        }
+        output.push('\n');
+    }
+    output
+}
+
 /// Return true if the given `path` is executable by any of the permission bits.
 ///
 /// # Parameters
@@ -24,6 +79,21 @@ pub fn is_executable(path: &Path) -> bool {
     }
 }
 
+/// Compare two file names the way `ls` orders them: on their alphanumeric
+/// characters only, ignoring case, falling back to the full names on ties.
+///
+/// # Parameters
+/// - `a`, `b`: file names to compare.
+pub fn compare_names(a: &str, b: &str) -> Ordering {
+    let key = |name: &str| {
+        name.chars()
+            .filter(|ch| ch.is_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase()
+    };
+    key(a).cmp(&key(b)).then_with(|| a.cmp(b))
+}
+
 /// Format UNIX permission bits into a human-readable permission string like `rwxr-xr-x`.
 ///
 /// # Parameters
@@ -82,11 +152,10 @@ pub fn format_permissions(permissions: &Permissions, path: &Path) -> String {
 /// - `metadata`: file metadata used to obtain UID.
 pub fn get_usr(metadata: &Metadata) -> User {
     let uid = metadata.uid();
-    let user = match get_user_by_uid(uid) {
+    match get_user_by_uid(uid) {
         Some(user) => user,
         None => User::new(uid, &uid.to_string(), metadata.gid()),
-    };
-    user
+    }
 }
 /// Return the `Group` corresponding to the GID in `metadata` or a synthetic one.
 ///
@@ -113,9 +182,9 @@ pub fn get_grp(metadata: &Metadata) -> Group {
 pub fn get_symlink_target_name(
     symlink_path: &PathBuf,
 ) -> Result<(Result<Metadata, std::io::Error>, String), String> {
-    let meta: Result<Metadata, std::io::Error> = fs::metadata(&symlink_path);
+    let meta: Result<Metadata, std::io::Error> = fs::metadata(symlink_path);
 
-    let target_path = match fs::read_link(&symlink_path) {
+    let target_path = match fs::read_link(symlink_path) {
         Ok(path) => path,
         Err(err) => {
             return Err(format!(
@@ -5,13 +5,14 @@ use std::fs::DirEntry;
 use std::fs::Metadata;
 use std::io;
 use std::io::ErrorKind;
+use std::io::Write;
 use std::os::unix::fs::FileTypeExt;
 use std::os::unix::fs::MetadataExt;
 use std::path::Path;
 use std::path::PathBuf;
 use term_size::dimensions;
 
-use crate::print_error;
+use crate::{Builtin, ShellState, Streams, interrupted, write_error};
 pub mod helpers;
 
 #[derive(Debug)]
@@ -54,6 +55,7 @@ struct Ls {
     files_names: Vec<String>,
     is_current: bool,
     ticket: bool,
+    colored: bool,
 }
 
 impl Ls {
@@ -69,6 +71,7 @@ impl Ls {
             files_names: Vec::new(),
             is_current: false,
             ticket: false,
+            colored: false,
         }
     }
 
@@ -84,7 +87,7 @@ impl Ls {
         };
 
         let metadata = fs::metadata(target_path).unwrap_or_else(|_| {
-            Metadata::from(fs::File::open("/dev/null").unwrap().metadata().unwrap())
+            fs::File::open("/dev/null").unwrap().metadata().unwrap()
         });
 
         let mut name = path.to_string();
@@ -110,6 +113,7 @@ impl Ls {
     /// - `entries`: list of `DirEntry` for a directory.
     /// - `file_name`: optional directory name displayed when multiple targets.
     /// - `is_total`: whether to include the `total` line when in long listing mode.
+    /// - `stderr`: stream errors are written to.
     ///
     /// # Returns
     /// - `String` containing formatted output for these entries.
@@ -118,16 +122,19 @@ impl Ls {
         entries: Vec<DirEntry>,
         file_name: Option<String>,
         is_total: bool,
+        stderr: &mut dyn Write,
     ) -> String {
         let mut res = Vec::new();
 
         // if self.files.len() > 1 {
         //     res.push(format!("{}:\n", file_name.unwrap()));
         // }
-        if !self.l_flag {
-            if !self.is_current && file_name.is_some() && self.ticket {
-                res.push(format!("{}:\n", file_name.clone().unwrap()));
-            }
+        if let Some(name) = &file_name
+            && !self.l_flag
+            && !self.is_current
+            && self.ticket
+        {
+            res.push(format!("{}:\n", name));
         }
 
         let mut max_user = 0;
@@ -150,8 +157,12 @@ impl Ls {
         }
 
         for entry in entries {
+            // Stop early on Ctrl+C, `ls` checks the flag before printing
+            if interrupted() {
+                break;
+            }
             let metadata = entry.metadata().unwrap_or_else(|_| {
-                Metadata::from(fs::File::open("/dev/null").unwrap().metadata().unwrap())
+                fs::File::open("/dev/null").unwrap().metadata().unwrap()
             });
             let mut file = Fileinfo::new(metadata.clone());
 
@@ -185,17 +196,15 @@ impl Ls {
             max_size = max_size.max(size_field.len());
             max_time_size = max_time_size.max(formatted_time.len());
 
-            let unsafe_characters = "*?[]$!\"\\;&|<> ()`~#=";
-
             let name = entry.file_name().to_string_lossy().into_owned();
             file.name = name.clone();
 
             for c in name.chars() {
-                if unsafe_characters.contains(c) {
-                    file.name = "'".to_string() + &file.name + &"'".to_string();
+                if UNSAFE_CHARACTERS.contains(c) {
+                    file.name = "'".to_string() + &file.name + "'";
                     break;
                 } else if "'".contains(c) {
-                    file.name = "\"".to_string() + &file.name + &"\"".to_string();
+                    file.name = "\"".to_string() + &file.name + "\"";
                     break;
                 }
             }
@@ -214,7 +223,7 @@ impl Ls {
                 let file_type = match entry.file_type() {
                     Ok(ft) => ft,
                     Err(err) => {
-                        eprintln!("Could not get file type: {}", err);
+                        write_error(stderr, &format!("Could not get file type: {}", err));
                         continue;
                     }
                 };
@@ -238,37 +247,17 @@ impl Ls {
             self.files.push(file);
         }
 
-        self.files.sort_by(|a, b| {
-            let a_tmp = a
-                .name
-                .chars()
-                .filter(|ch| ch.is_alphanumeric())
-                .collect::<String>();
-            let b_tmp = b
-                .name
-                .chars()
-                .filter(|ch| ch.is_alphanumeric())
-                .collect::<String>();
-            a_tmp.to_ascii_lowercase().cmp(&b_tmp.to_ascii_lowercase())
-        });
+        self.files.sort_by(|a, b| compare_names(&a.name, &b.name));
 
         let le = self.files.len();
         let term_width = dimensions().map(|(w, _)| w).unwrap_or(80);
-        let col_width = max_name_size + 2; // Add padding for spacing
-        let total_width = (le * col_width).saturating_sub(2); // Total width without last padding
-
-        // Determine number of rows and columns
-        let (num_cols, num_rows) = if total_width <= term_width {
-            // Single row if all files fit
-            (le, 1)
-        } else {
-            // Multiple columns based on terminal width
-            let num_cols = (term_width / col_width).max(1);
-            let num_rows = (le + num_cols - 1) / num_cols;
-            (num_cols, num_rows)
-        };
+        let (num_cols, num_rows, col_width) = grid_size(le, max_name_size, term_width);
 
         let mut matrix: Vec<Vec<String>> = vec![vec!["".to_string(); num_cols]; num_rows];
+        // Colours are only written to a terminal
+        let colored = self.colored;
+        let paint = |code: &'static str| if colored { code } else { "" };
+        let reset = paint("\x1b[0m");
         for (i, file) in self.files.iter_mut().enumerate() {
             // Get user and group info
             let user = helpers::get_usr(&file.metadata);
@@ -285,43 +274,42 @@ impl Ls {
                 let permissions = file.metadata.permissions();
                 let file_type = file.metadata.file_type();
 
-                let mut color = "\x1b[0m";
+                let mut color = paint("\x1b[0m");
                 if file.is_exec {
-                    color = "\x1b[1;32m";
+                    color = paint("\x1b[1;32m");
                 }
 
                 let type_char = if file_type.is_dir() {
-                    color = "\x1b[1;34m";
+                    color = paint("\x1b[1;34m");
                     'd'
                 } else if file_type.is_symlink() {
-                    color = "\x1b[1;36m";
-                    if let Some(en) = &file.entry {
-                        if let Ok((meta_data, mut name)) = helpers::get_symlink_target_name(&en) {
-                            match meta_data {
-                                Ok(meta) => {
-                                    let mut color2 = "\x1b[0m";
-                                    if meta.is_dir() {
-                                        color2 = "\x1b[1;34m";
-                                    } else if meta.is_file() && helpers::is_executable(&en) {
-                                        color2 = "\x1b[1;32m";
-                                    }
+                    color = paint("\x1b[1;36m");
+                    if let Some(en) = &file.entry
+                        && let Ok((meta_data, mut name)) = helpers::get_symlink_target_name(en)
+                    {
+                        match meta_data {
+                            Ok(meta) => {
+                                let mut color2 = paint("\x1b[0m");
+                                if meta.is_dir() {
+                                    color2 = paint("\x1b[1;34m");
+                                } else if meta.is_file() && helpers::is_executable(en) {
+                                    color2 = paint("\x1b[1;32m");
+                                }
 
-                                    if self.f_flag {
-                                        if meta.is_dir() {
-                                            name.push('/');
-                                        } else if meta.is_file() && helpers::is_executable(&en) {
-                                            name.push('*');
-                                        }
+                                if self.f_flag {
+                                    if meta.is_dir() {
+                                        name.push('/');
+                                    } else if meta.is_file() && helpers::is_executable(en) {
+                                        name.push('*');
                                     }
-                                    file.name =
-                                        format!("{}\x1b[0m -> {color2}{}\x1b[0m", file.name, name);
-                                }
-                                Err(_) => {
-                                    file.name = format!(
-                                        "\x1b[1;31m{}\x1b[0m -> \x1b[1;31m{}\x1b[0m",
-                                        file.name, name
-                                    );
                                 }
+                                file.name =
+                                    format!("{}{reset} -> {color2}{}{reset}", file.name, name);
+                            }
+                            Err(_) => {
+                                let red = paint("\x1b[1;31m");
+                                file.name =
+                                    format!("{red}{}{reset} -> {red}{}{reset}", file.name, name);
                             }
                         }
                     }
@@ -343,7 +331,7 @@ impl Ls {
                 let formatted_time = get_time(&file.metadata);
                 let perms = helpers::format_permissions(
                     &permissions,
-                    &file.entry.as_ref().unwrap_or(&PathBuf::new()),
+                    file.entry.as_ref().unwrap_or(&PathBuf::new()),
                 );
                 let hardlink = file.metadata.nlink();
                 let size_field = if file_type.is_char_device() || file_type.is_block_device() {
@@ -362,7 +350,7 @@ impl Ls {
                 };
 
                 res.push(format!(
-                    "{type_char}{perms} {hardlink:>width_links$} {user:<width_user$} {group:<width_group$} {size:>width_size$} {time:<width_time$} {color}{name}\x1b[0m{newline}",
+                    "{type_char}{perms} {hardlink:>width_links$} {user:<width_user$} {group:<width_group$} {size:>width_size$} {time:<width_time$} {color}{name}{reset}{newline}",
                     user = file.user,
                     group = file.group,
                     size = size_field,
@@ -382,21 +370,21 @@ impl Ls {
                 let row = i % num_rows;
                 let col = i / num_rows;
 
-                let mut color = "\x1b[0m";
+                let mut color = paint("\x1b[0m");
                 let meta = file.metadata.clone();
                 if meta.is_dir() {
-                    color = "\x1b[1;34m";
+                    color = paint("\x1b[1;34m");
                 } else if meta.is_symlink() {
-                    color = "\x1b[1;36m";
+                    color = paint("\x1b[1;36m");
                 } else if file.is_exec {
-                    color = "\x1b[1;32m";
+                    color = paint("\x1b[1;32m");
                 }
                 let padded_name = if num_rows == 1 {
                     format!("{} ", file.name)
                 } else {
                     format!("{:width$}", file.name, width = col_width)
                 };
-                matrix[row][col] = format!("{}{}\x1b[0m", color, padded_name);
+                matrix[row][col] = format!("{}{}{}", color, padded_name, reset);
             }
         }
 
@@ -407,8 +395,11 @@ impl Ls {
 
         if self.l_flag {
             let mut name = String::new();
-            if !self.is_current && file_name.is_some() && self.ticket {
-                name.push_str(&format!("{}:\n", file_name.unwrap()));
+            if let Some(file_name) = file_name
+                && !self.is_current
+                && self.ticket
+            {
+                name.push_str(&format!("{}:\n", file_name));
             }
             name + &total_lines + &res.join("")
         } else {
@@ -430,11 +421,22 @@ impl Ls {
 /// # Parameters
 /// - `tab`: arguments provided to `ls`.
 /// - `current_dir`: reference to the current working directory.
+/// - `colored`: whether names are coloured by type, when writing to a terminal.
+/// - `stdout`: stream the listing is written to.
+/// - `stderr`: stream errors are written to.
 ///
 /// # Returns
-/// - exit status code: `0` on success, non-zero on errors.
-pub fn ls(tab: &[String], current_dir: &PathBuf) -> i32 {
+/// - exit status code: `0` on success, `130` when interrupted with
+///   `Ctrl+C`, non-zero on errors.
+pub fn ls(
+    tab: &[String],
+    current_dir: &Path,
+    colored: bool,
+    stdout: &mut dyn Write,
+    stderr: &mut dyn Write,
+) -> i32 {
     let mut ls = Ls::new();
+    ls.colored = colored;
     let mut no_dir = vec![];
 
     for arg in tab {
@@ -445,18 +447,17 @@ pub fn ls(tab: &[String], current_dir: &PathBuf) -> i32 {
                     'F' => ls.f_flag = true,
                     'l' => ls.l_flag = true,
                     _ => {
-                        print_error("ls: invalid option -- '{ch}'");
+                        write_error(stderr, &format!("ls: invalid option -- '{ch}'"));
                         return 2;
                     }
                 }
             }
         } else {
-            let mut path = current_dir.clone();
-            path.push(arg.to_string());
+            let mut path = current_dir.to_path_buf();
+            path.push(arg);
             if !path.is_dir() {
-                match dir_entry_from_path(&path) {
-                    Ok(entry) => no_dir.push(entry),
-                    Err(_) => {}
+                if let Ok(entry) = dir_entry_from_path(&path) {
+                    no_dir.push(entry);
                 }
             } else {
                 ls.files_names.push(arg.to_string());
@@ -474,31 +475,24 @@ pub fn ls(tab: &[String], current_dir: &PathBuf) -> i32 {
     let le: usize = no_dir.len();
 
     if !no_dir.is_empty() {
-        output.push_str(&ls.myls(no_dir, None, false));
+        output.push_str(&ls.myls(no_dir, None, false, stderr));
         if !ls.files_names.is_empty() {
             output.push_str("\n\n");
         }
     }
 
     let mut files = ls.files_names.clone();
-    files.sort_by(|a, b| {
-        let a_tmp = a
-            .chars()
-            .filter(|ch| ch.is_alphanumeric())
-            .collect::<String>();
-        let b_tmp = b
-            .chars()
-            .filter(|ch| ch.is_alphanumeric())
-            .collect::<String>();
-        a_tmp.to_ascii_lowercase().cmp(&b_tmp.to_ascii_lowercase())
-    });
+    files.sort_by(|a, b| compare_names(a, b));
 
     ls.ticket = (files.len() + le) > 1;
 
     let mut err_status = 0;
 
     for (i, file_name) in files.iter().enumerate() {
-        let mut target_dir_str = current_dir.clone();
+        if interrupted() {
+            return 130;
+        }
+        let mut target_dir_str = current_dir.to_path_buf();
         target_dir_str.push(file_name);
         let mut prev_dir = PathBuf::new();
         if target_dir_str.is_dir() {
@@ -511,9 +505,9 @@ pub fn ls(tab: &[String], current_dir: &PathBuf) -> i32 {
         match fs::read_dir(&target_dir_str) {
             Ok(entries) => {
                 let filtered: Vec<_> = entries.filter_map(Result::ok).collect();
-                output.push_str(&ls.myls(filtered, Some(file_name.to_string()), true));
+                output.push_str(&ls.myls(filtered, Some(file_name.to_string()), true, stderr));
                 if i != files.len() - 1 {
-                    output.push_str("\n");
+                    output.push('\n');
                 }
             }
             Err(err) => {
@@ -529,14 +523,19 @@ pub fn ls(tab: &[String], current_dir: &PathBuf) -> i32 {
                     ),
                     _ => format!("ls: cannot access '{}': {}", file_name, err),
                 };
-                output.push_str(&error_message);
+                write_error(stderr, &error_message);
             }
         }
         if files.len() > 1 && i != files.len() - 1 {
             output.push('\n');
         }
     }
-    println!("{output}");
+    if interrupted() {
+        return 130;
+    }
+    if writeln!(stdout, "{output}").is_err() {
+        return 1;
+    }
     err_status
 }
 
@@ -565,3 +564,16 @@ fn dir_entry_from_path(path: &Path) -> io::Result<DirEntry> {
         .find(|entry| entry.file_name().to_string_lossy() == file_name)
         .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "File not found in directory"))
 }
+
+/// `ls` builtin, see [`ls`].
+pub struct LsBuiltin;
+
+impl Builtin for LsBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        ls(args, &state.current_dir, io.terminal, io.stdout, io.stderr)
+    }
+
+    fn flags(&self) -> &str {
+        "alF"
+    }
+}
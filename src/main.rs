@@ -1,76 +1,101 @@
-use ctrlc;
 use shell::*;
-use std::env::*;
+use std::env;
+use std::fs;
 use std::io;
+use std::io::ErrorKind;
 use std::io::Write;
-use std::io::stdin;
 use std::io::stdout;
-use std::path::PathBuf;
-use std::process::exit;
+use std::process;
 
-/// Execute a built-in command by name, delegating to the appropriate module.
+/// Entry point. Runs the command string of `-c`, the script named by the
+/// first operand, or the commands of stdin when it is not a terminal, then
+/// exits with the status of the last command. Otherwise prints the banner,
+/// turns on job control, runs the startup files unless `--norc` is given,
+/// and starts the REPL.
 ///
-/// # Parameters
-/// - `command`: command name (e.g., "ls").
-/// - `args`: arguments passed to the command.
-/// - `current_dir`: current working directory (may be modified by commands).
-/// - `history_current_dir`: previous directory used for `cd -` behavior.
-/// - `hist`: reference to the command history.
-/// - `home`: user's home directory path.
-/// - `last_command_staus`: exit status of the last command executed.
+/// Options `-e` and `-x` (or `+e`, `+x`) set `errexit` and `xtrace` as
+/// `set` does; operands after `-c` give `$0` and the positional parameters,
+/// and those after a script its positional parameters.
 ///
 /// # Returns
-/// - exit status code (i32) of the executed command.
-fn exec_command(
-    command: &str,
-    args: &[String],
-    current_dir: &mut PathBuf,
-    history_current_dir: &mut PathBuf,
-    hist: &Vec<String>,
-    home: &PathBuf,
-    last_command_staus: i32,
-) -> i32 {
-    match command {
-        "echo" => echo(args),
-        "pwd" => pwd(current_dir),
-        "cd" => cd(args, history_current_dir, current_dir, home),
-        "mv" => mv(&args),
-        "cp" => cp(&args),
-        "ls" => ls(&args, &current_dir),
-        "cat" => cat(args, current_dir),
-        "rm" => rm(args, current_dir),
-        "mkdir" => mkdir(args, current_dir),
-        "history" => history(hist),
-        "exit" => {
-            if args.len() == 0 {
-                exit(last_command_staus);
-            } else {
-                match args[0].parse::<i32>() {
-                    Ok(code) => exit(code),
-                    Err(_) => {
-                        print_error("exit: Illegal number: ");
-                        2
+/// - `Ok(())` on clean exit, or an `io::Error` if writing to stdout/stderr fails.
+fn main() -> Result<(), io::Error> {
+    let args = env::args().collect::<Vec<_>>();
+    let mut state = ShellState::new();
+    if catch_interrupts().is_err() {
+        print_error("Error setting Ctrl+C handler");
+    };
+
+    let mut norc = false;
+    let mut command = false;
+    let mut i = 1;
+    while let Some(arg) = args.get(i) {
+        match arg.as_str() {
+            "--norc" => norc = true,
+            "--" => {
+                i += 1;
+                break;
+            }
+            _ if arg.len() > 1 && (arg.starts_with('-') || arg.starts_with('+')) => {
+                for letter in arg.chars().skip(1) {
+                    let option = Options::LETTERS
+                        .iter()
+                        .find(|(l, _)| *l == letter)
+                        .and_then(|(_, name)| state.options.get_mut(name));
+                    match option {
+                        Some(option) => *option = arg.starts_with('-'),
+                        None if letter == 'c' => command = true,
+                        None => {
+                            print_error(&format!("{}: invalid option", arg));
+                            process::exit(2);
+                        }
                     }
                 }
             }
+            _ => break,
         }
-        "clear" => {
-            println!("\x1Bc");
-            0
-        }
-        _ => {
-            print_error(&format!("Command <{}\x1b[31m> not found", command));
-            127
+        i += 1;
+    }
+    let operands = &args[i..];
+
+    if command {
+        let Some(text) = operands.first() else {
+            print_error("-c: option requires an argument");
+            process::exit(2);
+        };
+        if let Some(name) = operands.get(1) {
+            state.arg0 = name.clone();
         }
+        state.positional = operands.iter().skip(2).cloned().collect();
+        let name = state.arg0.clone();
+        let status = run_script(text, &name, &mut state);
+        exit_shell(status, &mut state);
+    }
+    if let Some(path) = operands.first() {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(err) => {
+                print_error(&format!("{}: {}", path, err));
+                process::exit(if err.kind() == ErrorKind::NotFound {
+                    127
+                } else {
+                    126
+                });
+            }
+        };
+        state.arg0 = path.clone();
+        state.positional = operands[1..].to_vec();
+        let status = run_script(&source, path, &mut state);
+        exit_shell(status, &mut state);
+    }
+    if unsafe { libc::isatty(libc::STDIN_FILENO) } == 0 {
+        let name = state.arg0.clone();
+        let status = run_lines(read_plain_line, &name, &mut state);
+        exit_shell(status, &mut state);
     }
-}
 
-/// Main REPL loop: prints prompt, reads input, parses and executes commands.
-///
-/// # Returns
-/// - `Ok(())` on clean exit, or an `io::Error` if writing to stdout/stderr fails.
-fn main() -> Result<(),io::Error> {
-    write!(stdout(),
+    write!(
+        stdout(),
         "\x1b[1;31m
      ██████╗     ███████╗██╗  ██╗███████╗██╗     ██╗     
     ██╔═████╗    ██╔════╝██║  ██║██╔════╝██║     ██║     
@@ -83,86 +108,10 @@ fn main() -> Result<(),io::Error> {
     \x1b[1;0m"
     )?;
 
-    // set_current_dir(path)
-    let mut history_current_dir = current_dir().unwrap_or(PathBuf::from("/"));
-    let mut current_dir = history_current_dir.clone();
-    let mut hist: Vec<String> = Vec::new();
-    let home = match home_dir() {
-        Some(p) => p,
-        None => {
-            print_error("Impossible to get your home dir!");
-            current_dir.clone()
-        }
-    };
-
-    let mut last_command_staus = 0;
-    if let Err(_) = ctrlc::set_handler(|| {}) {
-        print_error("Error setting Ctrl+C handler");
-    };
-
-    loop {
-        let address = match current_dir.strip_prefix(&home) {
-            Ok(p) => "\x1b[1;31m~\x1b[1;36m/".to_string() + &p.display().to_string(),
-            Err(_) => current_dir.display().to_string(),
-        };
-
-        print!("\x1b[1;33m➜  \x1b[1;36m{} \x1b[33m$ \x1b[0m", address);
-        std::io::stdout().flush()?;
-        let mut entry = String::new();
-        let size = stdin().read_line(&mut entry).unwrap();
-        if size == 0 {
-            println!();
-            exit(0);
-        }
-
-        let (mut command, mut open_quote) = entry.custom_split();
-        if open_quote {
-            loop {
-                print!("\x1b[33m> \x1b[0m");
-                let mut input_tmp = String::new();
-
-                std::io::stdout().flush()?;
-
-                let size = stdin().read_line(&mut input_tmp).unwrap();
-
-                if size == 0 {
-                    break;
-                }
-
-                entry.push_str(&input_tmp);
-                let (input_tmp, open_quote2) = entry.custom_split();
-                open_quote = open_quote2;
-                command = input_tmp;
-                if !open_quote {
-                    break;
-                }
-            }
-        }
-
-        if command.name.is_empty() {
-            continue;
-        }
-
-        if open_quote {
-            print_error("Syntax error: Unterminated quoted string");
-            continue;
-        }
-
-        let output = exec_command(
-            &command.name,
-            &command.args,
-            &mut current_dir,
-            &mut history_current_dir,
-            &hist,
-            &home,
-            last_command_staus,
-        );
-        
-        last_command_staus = output;
-
-        // Add to history if entry has non-whitespace characters
-        if entry.split_whitespace().collect::<Vec<_>>().len() != 0 {
-            hist.push(entry.clone());
-        }
+    init_job_control(&mut state);
+    if !norc {
+        run_startup_files(&mut state);
     }
+
+    repl(&mut state)
 }
@@ -1,19 +1,21 @@
 use std::fs;
-use std::path::PathBuf;
-use crate::print_error;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use crate::{Builtin, Candidate, ShellState, Streams, write_error};
 
 /// Create directories specified in `args` relative to `current_dir`.
 ///
 /// # Parameters
 /// - `args`: list of directory names to create (absolute or relative).
 /// - `current_dir`: base directory for relative paths.
+/// - `stderr`: stream errors are written to.
 ///
 /// # Returns
 /// - `0` on success, non-zero on errors.
-pub fn mkdir(args: &[String], current_dir: &PathBuf) -> i32 {
+pub fn mkdir(args: &[String], current_dir: &Path, stderr: &mut dyn Write) -> i32 {
     // Check if any directory arguments are provided
     if args.is_empty() {
-        print_error("mkdir: missing operand");
+        write_error(stderr, "mkdir: missing operand");
         return 1;
     }
 
@@ -27,8 +29,27 @@ pub fn mkdir(args: &[String], current_dir: &PathBuf) -> i32 {
         };
 
         if let Err(e) = fs::create_dir(&target) {
-            print_error(&format!("mkdir: cannot create directory '{}': {}", arg, e));
+            write_error(stderr, &format!("mkdir: cannot create directory '{}': {}", arg, e));
         }
     }
     0
-}
\ No newline at end of file
+}
+
+/// `mkdir` builtin, see [`mkdir`].
+pub struct MkdirBuiltin;
+
+impl Builtin for MkdirBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        mkdir(args, &state.current_dir, io.stderr)
+    }
+
+    /// The directories to create do not exist yet: nothing to offer.
+    fn complete(
+        &self,
+        _args: &[String],
+        _word: &str,
+        _state: &mut ShellState,
+    ) -> Option<Vec<Candidate>> {
+        Some(Vec::new())
+    }
+}
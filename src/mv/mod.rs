@@ -1,30 +1,34 @@
 use std::fs;
+use std::io::Write;
 use std::path::Path;
 
-use crate::print_error;
+use crate::{Builtin, ShellState, Streams, write_error};
 
 /// Move or rename files. If multiple sources are provided, destination must be a directory.
 ///
 /// # Parameters
 /// - `args`: list of source paths followed by destination path.
+/// - `current_dir`: base directory for relative paths.
+/// - `stderr`: stream errors are written to.
 ///
 /// # Returns
 /// - `0` on success, non-zero on errors.
-pub fn mv(args: &[String]) -> i32 {
+pub fn mv(args: &[String], current_dir: &Path, stderr: &mut dyn Write) -> i32 {
     // Check for missing source or destination operands
     if args.is_empty() {
-        print_error("mv: missing file operand");
+        write_error(stderr, "mv: missing file operand");
         return 1;
     }
     if args.len() == 1 {
-        print_error(&format!("mv: missing destination file operand after '{}'", args[0]));
+        write_error(stderr, &format!("mv: missing destination file operand after '{}'", args[0]));
         return 1;
     }
-    let last = Path::new(&args[args.len() - 1]);
+    let last_str = &args[args.len() - 1];
+    let last = current_dir.join(last_str);
     let sources = &args[..args.len() - 1];
     // Validate that destination is a directory when moving multiple files
     if sources.len() > 1 && !last.is_dir() {
-        print_error(&format!("mv: target '{}' is not a directory", last.display()));
+        write_error(stderr, &format!("mv: target '{}' is not a directory", last_str));
         return 1;
     }
     for src_str in sources {
@@ -32,43 +36,46 @@ pub fn mv(args: &[String]) -> i32 {
             continue;
         }
 
-        let src = Path::new(src_str);
+        let src = current_dir.join(src_str);
 
         if !src.exists() {
-            print_error(&format!("mv: cannot stat '{}': No such file or directory", src.display()));
+            write_error(stderr, &format!("mv: cannot stat '{}': No such file or directory", src_str));
             continue;
         }
 
-        let dst_path = if last.is_dir() {
+        let (dst_path, dst_str) = if last.is_dir() {
             match src.file_name() {
-                Some(name) => last.join(name),
+                Some(name) => {
+                    let dst_str = Path::new(last_str).join(name).display().to_string();
+                    (last.join(name), dst_str)
+                }
                 None => {
-                    print_error(&format!("mv: cannot move '{}': invalid file name", src.display()));
+                    write_error(stderr, &format!("mv: cannot move '{}': invalid file name", src_str));
                     continue;
                 }
             }
         } else {
-            last.to_path_buf()
+            (last.to_path_buf(), last_str.to_string())
         };
 
-        if fs::canonicalize(src).ok() == fs::canonicalize(&dst_path).ok() {
-            print_error(&format!("mv: '{}' and '{}' are the same file", src.display(), dst_path.display()));
+        if fs::canonicalize(&src).ok() == fs::canonicalize(&dst_path).ok() {
+            write_error(stderr, &format!("mv: '{}' and '{}' are the same file", src_str, dst_str));
             continue;
         }
 
-        if let Err(e) = fs::rename(src, &dst_path) {
-            print_error(&format!("mv: rename failed '{}': {}", src.display(), e));
-            match fs::copy(src, &dst_path) {
+        if let Err(e) = fs::rename(&src, &dst_path) {
+            write_error(stderr, &format!("mv: rename failed '{}': {}", src_str, e));
+            match fs::copy(&src, &dst_path) {
                 Ok(_) => {
-                    if let Err(e) = fs::remove_file(src) {
-                        print_error(&format!("mv: cannot remove '{}': {}", src.display(), e));
+                    if let Err(e) = fs::remove_file(&src) {
+                        write_error(stderr, &format!("mv: cannot remove '{}': {}", src_str, e));
                     }
                 }
                 Err(e) => {
-                    print_error(&format!(
+                    write_error(stderr, &format!(
                         "mv: cannot move '{}' to '{}': {}",
-                        src.display(),
-                        dst_path.display(),
+                        src_str,
+                        dst_str,
                         e)
                     );
                 }
@@ -76,4 +83,13 @@ pub fn mv(args: &[String]) -> i32 {
         }
     }
     0
-}
\ No newline at end of file
+}
+
+/// `mv` builtin, see [`mv`].
+pub struct MvBuiltin;
+
+impl Builtin for MvBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        mv(args, &state.current_dir, io.stderr)
+    }
+}
@@ -0,0 +1,277 @@
+use super::*;
+
+impl Parser {
+    /// Consume the reserved word `word`, or fail on whatever is there.
+    fn expect(&mut self, word: &str) -> Result<(), ParseError> {
+        self.expect_token(&Token::Word(word.to_string()))
+    }
+
+    /// Consume the token `expected`, or fail on whatever is there.
+    fn expect_token(&mut self, expected: &Token) -> Result<(), ParseError> {
+        match self.next() {
+            Some(token) if token == *expected => Ok(()),
+            Some(token) => Err(Self::unexpected(&token)),
+            None => Err(Self::end_of_file()),
+        }
+    }
+
+    /// Return `true` when the tokens at `at` are `(` and `)`.
+    fn parens_at(&self, at: usize) -> bool {
+        self.tokens.get(at) == Some(&Token::LParen)
+            && self.tokens.get(at + 1) == Some(&Token::RParen)
+    }
+
+    /// `function := name "(" ")" newline* compound redirect*
+    ///             | "function" name ("(" ")")? newline* compound redirect*`
+    ///
+    /// # Returns
+    /// - `None` when the current tokens do not start a function definition.
+    pub(super) fn function(&mut self) -> Result<Option<Function>, ParseError> {
+        let name = match self.peek_word() {
+            Some("function") => {
+                self.pos += 1;
+                let name = match self.next() {
+                    Some(Token::Word(name)) => name,
+                    Some(token) => return Err(Self::unexpected(&token)),
+                    None => return Err(Self::end_of_file()),
+                };
+                if self.parens_at(self.pos) {
+                    self.pos += 2;
+                }
+                name
+            }
+            Some(name) if !RESERVED_WORDS.contains(&name) && self.parens_at(self.pos + 1) => {
+                let name = name.to_string();
+                self.pos += 3;
+                name
+            }
+            _ => return Ok(None),
+        };
+        if !is_function_name(&name) {
+            let message = "Syntax error: Bad function name";
+            return Err(ParseError::Syntax(message.to_string()));
+        }
+
+        self.skip_newlines();
+        let Some(body) = self.compound()? else {
+            return Err(match self.peek() {
+                Some(token) => Self::unexpected(token),
+                None => Self::end_of_file(),
+            });
+        };
+        Ok(Some(Function {
+            name,
+            body,
+            redirects: self.redirects()?,
+        }))
+    }
+
+    /// `compound := if_clause | while_clause | for_clause | case_clause
+    ///             | "{" list "}" | "(" list ")"`
+    ///
+    /// # Returns
+    /// - `None` when the current token does not start a compound command.
+    pub(super) fn compound(&mut self) -> Result<Option<Compound>, ParseError> {
+        let compound = match self.peek() {
+            Some(Token::LParen) => {
+                self.pos += 1;
+                let body = self.body(&[])?;
+                self.expect_token(&Token::RParen)?;
+                Compound::Subshell(body)
+            }
+            Some(Token::Word(word)) => match word.as_str() {
+                "if" => self.if_clause()?,
+                "while" | "until" => self.while_clause()?,
+                "for" => self.for_clause()?,
+                "case" => self.case_clause()?,
+                "{" => {
+                    self.pos += 1;
+                    let body = self.body(&["}"])?;
+                    self.expect("}")?;
+                    Compound::Group(body)
+                }
+                _ => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+        Ok(Some(compound))
+    }
+
+    /// `compound_list := newline* (and_or (";" | "&" | newline)*)*`, stopping
+    /// before one of the reserved words `ends` found in place of a command,
+    /// a `)` or a `;;`, left for the caller to check.
+    fn compound_list(&mut self, ends: &[&str]) -> Result<List, ParseError> {
+        let mut list = List::default();
+        loop {
+            self.skip_newlines();
+            match self.peek() {
+                None => return Err(Self::end_of_file()),
+                Some(Token::RParen | Token::DSemi) => return Ok(list),
+                Some(Token::Word(word)) if ends.contains(&word.as_str()) => return Ok(list),
+                _ => {}
+            }
+            let and_or = self.and_or()?;
+            let background = and_or.background;
+            list.items.push(and_or);
+            match self.peek() {
+                Some(Token::Newline) => self.pos += 1,
+                Some(Token::Semi) if !background => self.pos += 1,
+                None | Some(Token::RParen | Token::DSemi) => {}
+                Some(Token::Word(word)) if ends.contains(&word.as_str()) => {}
+                _ if background => {}
+                Some(token) => return Err(Self::unexpected(token)),
+            }
+        }
+    }
+
+    /// A `compound_list` holding at least one command, as required for
+    /// every part of a compound command but the items of `case`.
+    fn body(&mut self, ends: &[&str]) -> Result<List, ParseError> {
+        let list = self.compound_list(ends)?;
+        if list.is_empty() {
+            return Err(match self.peek() {
+                Some(token) => Self::unexpected(token),
+                None => Self::end_of_file(),
+            });
+        }
+        Ok(list)
+    }
+
+    /// `if_clause := "if" list "then" list ("elif" list "then" list)*
+    ///               ("else" list)? "fi"`
+    fn if_clause(&mut self) -> Result<Compound, ParseError> {
+        self.pos += 1;
+        let mut branches = Vec::new();
+        let mut otherwise = None;
+        loop {
+            let condition = self.body(&["then"])?;
+            self.expect("then")?;
+            branches.push((condition, self.body(&["elif", "else", "fi"])?));
+            match self.next() {
+                Some(Token::Word(word)) if word == "elif" => {}
+                Some(Token::Word(word)) if word == "else" => {
+                    otherwise = Some(self.body(&["fi"])?);
+                    self.expect("fi")?;
+                    break;
+                }
+                Some(Token::Word(word)) if word == "fi" => break,
+                Some(token) => return Err(Self::unexpected(&token)),
+                None => return Err(Self::end_of_file()),
+            }
+        }
+        Ok(Compound::If {
+            branches,
+            otherwise,
+        })
+    }
+
+    /// `while_clause := ("while" | "until") list do_group`
+    fn while_clause(&mut self) -> Result<Compound, ParseError> {
+        let until = self.peek_word() == Some("until");
+        self.pos += 1;
+        let condition = self.body(&["do"])?;
+        Ok(Compound::While {
+            condition,
+            body: self.do_group()?,
+            until,
+        })
+    }
+
+    /// `do_group := "do" list "done"`
+    fn do_group(&mut self) -> Result<List, ParseError> {
+        self.expect("do")?;
+        let body = self.body(&["done"])?;
+        self.expect("done")?;
+        Ok(body)
+    }
+
+    /// `for_clause := "for" name newline* ("in" word* (";" | newline))?
+    ///                ";"? newline* do_group`
+    fn for_clause(&mut self) -> Result<Compound, ParseError> {
+        self.pos += 1;
+        let name = match self.next() {
+            Some(Token::Word(name)) if is_name(&name) => name,
+            Some(Token::Word(_)) => {
+                let message = "Syntax error: Bad for loop variable";
+                return Err(ParseError::Syntax(message.to_string()));
+            }
+            Some(token) => return Err(Self::unexpected(&token)),
+            None => return Err(Self::end_of_file()),
+        };
+        self.skip_newlines();
+
+        let mut words = None;
+        if self.peek_word() == Some("in") {
+            self.pos += 1;
+            let mut list = Vec::new();
+            while let Some(Token::Word(word)) = self.peek() {
+                list.push(word.clone());
+                self.pos += 1;
+            }
+            match self.next() {
+                Some(Token::Semi | Token::Newline) => {}
+                Some(token) => return Err(Self::unexpected(&token)),
+                None => return Err(Self::end_of_file()),
+            }
+            words = Some(list);
+        } else if self.peek() == Some(&Token::Semi) {
+            self.pos += 1;
+        }
+        self.skip_newlines();
+
+        Ok(Compound::For {
+            name,
+            words,
+            body: self.do_group()?,
+        })
+    }
+
+    /// `case_clause := "case" word newline* "in" newline*
+    ///                 ("("? word ("|" word)* ")" list ";;"? newline*)* "esac"`
+    fn case_clause(&mut self) -> Result<Compound, ParseError> {
+        self.pos += 1;
+        let word = match self.next() {
+            Some(Token::Word(word)) => word,
+            Some(token) => return Err(Self::unexpected(&token)),
+            None => return Err(Self::end_of_file()),
+        };
+        self.skip_newlines();
+        self.expect("in")?;
+
+        let mut items = Vec::new();
+        loop {
+            self.skip_newlines();
+            match self.peek() {
+                Some(Token::Word(word)) if word == "esac" => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(Token::LParen) => self.pos += 1,
+                _ => {}
+            }
+            let mut patterns = Vec::new();
+            loop {
+                match self.next() {
+                    Some(Token::Word(pattern)) => patterns.push(pattern),
+                    Some(token) => return Err(Self::unexpected(&token)),
+                    None => return Err(Self::end_of_file()),
+                }
+                match self.next() {
+                    Some(Token::Pipe) => {}
+                    Some(Token::RParen) => break,
+                    Some(token) => return Err(Self::unexpected(&token)),
+                    None => return Err(Self::end_of_file()),
+                }
+            }
+            let body = self.compound_list(&["esac"])?;
+            items.push(CaseItem { patterns, body });
+            match self.peek() {
+                Some(Token::DSemi) => self.pos += 1,
+                Some(Token::Word(word)) if word == "esac" => {}
+                Some(token) => return Err(Self::unexpected(token)),
+                None => return Err(Self::end_of_file()),
+            }
+        }
+        Ok(Compound::Case { word, items })
+    }
+}
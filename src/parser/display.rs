@@ -0,0 +1,224 @@
+use super::*;
+use std::fmt;
+
+/// Indentation of one nesting level.
+const INDENT: &str = "    ";
+
+/// Write a redirection back as it can be typed, leaving out the default
+/// descriptor of its operator.
+fn format_redirect(redirect: &Redirect) -> String {
+    let (operator, default_fd) = match redirect.kind {
+        RedirectKind::Input => ("<", 0),
+        RedirectKind::Output => (">", 1),
+        RedirectKind::Append => (">>", 1),
+        RedirectKind::Duplicate if redirect.fd == 0 => ("<&", 0),
+        RedirectKind::Duplicate => (">&", 1),
+    };
+    let fd = if redirect.fd == default_fd {
+        String::new()
+    } else {
+        redirect.fd.to_string()
+    };
+    format!("{}{}{}", fd, operator, redirect.target)
+}
+
+/// Append the redirections of a command after its text.
+fn with_redirects(text: String, redirects: &[Redirect]) -> String {
+    std::iter::once(text)
+        .chain(redirects.iter().map(format_redirect))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Write a simple command on one line, its words raw.
+fn format_command(command: &Command) -> String {
+    let words = command
+        .assignments
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .chain(std::iter::once(command.name.clone()).filter(|name| !name.is_empty()))
+        .chain(command.args.iter().cloned())
+        .chain(command.redirects.iter().map(format_redirect));
+    words.collect::<Vec<_>>().join(" ")
+}
+
+/// Indentation of `indent` levels, nothing on a single line.
+fn indentation(indent: Option<usize>) -> String {
+    INDENT.repeat(indent.unwrap_or(0))
+}
+
+/// Text ending a line, a space on a single line.
+fn newline(indent: Option<usize>) -> &'static str {
+    if indent.is_some() { "\n" } else { " " }
+}
+
+/// Write an and-or list, followed by `&` when it runs in the background.
+/// Compound commands span several lines, whose inner lines are indented
+/// one level deeper than `indent`, or stay on a single line without
+/// `indent`.
+fn format_and_or(and_or: &AndOr, indent: Option<usize>) -> String {
+    let mut text = format_pipeline(&and_or.first, indent);
+    for (connector, pipeline) in &and_or.rest {
+        text.push_str(match connector {
+            Connector::And => " && ",
+            Connector::Or => " || ",
+        });
+        text.push_str(&format_pipeline(pipeline, indent));
+    }
+    if and_or.background {
+        text.push_str(" &");
+    }
+    text
+}
+
+fn format_pipeline(pipeline: &Pipeline, indent: Option<usize>) -> String {
+    let stages = pipeline
+        .commands
+        .iter()
+        .map(|stage| match stage {
+            Stage::Simple(command) => format_command(command),
+            Stage::Compound(compound, redirects) => {
+                with_redirects(format_compound(compound, indent), redirects)
+            }
+            Stage::Function(function) => format_function(function, indent),
+        })
+        .collect::<Vec<_>>()
+        .join(" | ");
+    if pipeline.negated {
+        format!("! {}", stages)
+    } else {
+        stages
+    }
+}
+
+/// Write a list on a single line, as for the condition of `if`.
+fn format_inline(list: &List, indent: Option<usize>) -> String {
+    let mut text = String::new();
+    for (i, and_or) in list.items.iter().enumerate() {
+        if i > 0 {
+            // `&` already separates a background list from the next one
+            let separator = if list.items[i - 1].background {
+                " "
+            } else {
+                "; "
+            };
+            text.push_str(separator);
+        }
+        text.push_str(&format_and_or(and_or, indent));
+    }
+    text
+}
+
+/// Write a list one and-or list per line, indented by `indent` levels, or
+/// every and-or list followed by `;` on a single line.
+fn format_body(list: &List, indent: Option<usize>) -> String {
+    let mut text = String::new();
+    for and_or in &list.items {
+        text.push_str(&indentation(indent));
+        text.push_str(&format_and_or(and_or, indent));
+        text.push_str(match indent {
+            Some(_) => "\n",
+            None if and_or.background => " ",
+            None => "; ",
+        });
+    }
+    text
+}
+
+fn format_compound(compound: &Compound, indent: Option<usize>) -> String {
+    let pad = indentation(indent);
+    let newline = newline(indent);
+    let inner = indent.map(|indent| indent + 1);
+    match compound {
+        Compound::If {
+            branches,
+            otherwise,
+        } => {
+            let mut text = String::new();
+            for (i, (condition, body)) in branches.iter().enumerate() {
+                if i > 0 {
+                    text.push_str(&pad);
+                    text.push_str("el");
+                }
+                let condition = format_inline(condition, indent);
+                text.push_str(&format!("if {}; then{}", condition, newline));
+                text.push_str(&format_body(body, inner));
+            }
+            if let Some(body) = otherwise {
+                let body = format_body(body, inner);
+                text.push_str(&format!("{}else{}{}", pad, newline, body));
+            }
+            text + &pad + "fi"
+        }
+        Compound::While {
+            condition,
+            body,
+            until,
+        } => format!(
+            "{} {}; do{}{}{}done",
+            if *until { "until" } else { "while" },
+            format_inline(condition, indent),
+            newline,
+            format_body(body, inner),
+            pad
+        ),
+        Compound::For { name, words, body } => {
+            let words = match words {
+                Some(words) => format!(" in {}", words.join(" ")),
+                None => String::new(),
+            };
+            let body = format_body(body, inner);
+            format!("for {}{}; do{}{}{}done", name, words, newline, body, pad)
+        }
+        Compound::Case { word, items } => {
+            let body_indent = inner.map(|inner| inner + 1);
+            let mut text = format!("case {} in{}", word, newline);
+            for item in items {
+                let patterns = item.patterns.join(" | ");
+                text.push_str(&format!("{}{}){}", indentation(inner), patterns, newline));
+                text.push_str(&format_body(&item.body, body_indent));
+                text.push_str(&format!("{};;{}", indentation(body_indent), newline));
+            }
+            text + &pad + "esac"
+        }
+        Compound::Group(list) => format!("{{{}{}{}}}", newline, format_body(list, inner), pad),
+        Compound::Subshell(list) => format!("({}{}{})", newline, format_body(list, inner), pad),
+    }
+}
+
+fn format_function(function: &Function, indent: Option<usize>) -> String {
+    let body = format_compound(&function.body, indent);
+    with_redirects(format!("{}() {}", function.name, body), &function.redirects)
+}
+
+impl fmt::Display for Command {
+    /// Write the command on one line with its words raw, so it parses back
+    /// to the same command.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", format_command(self))
+    }
+}
+
+impl fmt::Display for AndOr {
+    /// Write the and-or list back as shell code on a single line, as the
+    /// text of a job.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", format_and_or(self, None))
+    }
+}
+
+impl fmt::Display for Pipeline {
+    /// Write the pipeline back as shell code on a single line, as the text
+    /// of a job.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", format_pipeline(self, None))
+    }
+}
+
+impl fmt::Display for Function {
+    /// Write the definition back as shell code, indented with four spaces,
+    /// so it can be read back to define the same function.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", format_function(self, Some(0)))
+    }
+}
@@ -0,0 +1,344 @@
+use super::*;
+
+/// A token of the shell language.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Token {
+    Word(String), // Raw word, quotes and `$` expressions included
+    // `<`, `>`, `>>`, `>|`, `>&` or `<&`, with the descriptor it rebinds
+    Redirect { fd: i32, kind: RedirectKind },
+    Pipe,    // `|`
+    AndIf,   // `&&`
+    Amp,     // `&`, running the and-or list before it in the background
+    OrIf,    // `||`
+    Semi,    // `;`
+    DSemi,   // `;;`, ending a `case` item
+    LParen,  // `(`
+    RParen,  // `)`
+    Newline, // End of a line
+}
+
+impl Token {
+    /// Return the token as written in the input, for error messages.
+    pub fn text(&self) -> String {
+        match self {
+            Token::Word(word) => word.clone(),
+            Token::Redirect { kind, .. } => match kind {
+                RedirectKind::Input => "<",
+                RedirectKind::Output => ">",
+                RedirectKind::Append => ">>",
+                RedirectKind::Duplicate => ">&",
+            }
+            .to_string(),
+            Token::Pipe => "|".to_string(),
+            Token::AndIf => "&&".to_string(),
+            Token::Amp => "&".to_string(),
+            Token::OrIf => "||".to_string(),
+            Token::Semi => ";".to_string(),
+            Token::DSemi => ";;".to_string(),
+            Token::LParen => "(".to_string(),
+            Token::RParen => ")".to_string(),
+            Token::Newline => "newline".to_string(),
+        }
+    }
+}
+
+/// Split the input into tokens on whitespace and unquoted operators while
+/// honoring single and double quotes and backslash escapes, and drop
+/// comments. Words are kept raw, quotes included, for `expand_command`.
+///
+/// # Returns
+/// - the tokens, or `ParseError::Incomplete` when there is an unterminated
+///   quote, a trailing backslash or an unclosed `${`, `$(` or backquote.
+pub fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
+    let chars = input.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+
+    /// Hand a finished word over to the token list.
+    fn end_word(tokens: &mut Vec<Token>, word: &mut String) {
+        if !word.is_empty() {
+            tokens.push(Token::Word(std::mem::take(word)));
+        }
+    }
+
+    let mut i = 0;
+    while i < chars.len() {
+        let ch = chars[i];
+        match ch {
+            '\\' => match chars.get(i + 1) {
+                // A backslash before a newline joins the lines
+                Some('\n') => i += 1,
+                Some(&next) => {
+                    word.push(ch);
+                    word.push(next);
+                    i += 1;
+                }
+                None => return Err(unterminated()),
+            },
+            '\'' => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&c| c == '\'')
+                    .ok_or_else(unterminated)?;
+                word.extend(&chars[i..=i + 1 + end]);
+                i += 1 + end;
+            }
+            '"' => i = double_quote(&chars, i, &mut word)?,
+            '$' => i = dollar(&chars, i, &mut word)?,
+            '`' => i = backquote(&chars, i, &mut word)?,
+            '\n' => {
+                end_word(&mut tokens, &mut word);
+                tokens.push(Token::Newline);
+            }
+            // A `#` starting a word starts a comment, up to the end of the line
+            '#' if word.is_empty() => {
+                while i + 1 < chars.len() && chars[i + 1] != '\n' {
+                    i += 1;
+                }
+            }
+            _ if ch.is_whitespace() => end_word(&mut tokens, &mut word),
+            '|' => {
+                end_word(&mut tokens, &mut word);
+                if chars.get(i + 1) == Some(&'|') {
+                    i += 1;
+                    tokens.push(Token::OrIf);
+                } else {
+                    tokens.push(Token::Pipe);
+                }
+            }
+            '&' => {
+                end_word(&mut tokens, &mut word);
+                if chars.get(i + 1) == Some(&'&') {
+                    i += 1;
+                    tokens.push(Token::AndIf);
+                } else {
+                    tokens.push(Token::Amp);
+                }
+            }
+            ';' => {
+                end_word(&mut tokens, &mut word);
+                if chars.get(i + 1) == Some(&';') {
+                    i += 1;
+                    tokens.push(Token::DSemi);
+                } else {
+                    tokens.push(Token::Semi);
+                }
+            }
+            '(' | ')' => {
+                end_word(&mut tokens, &mut word);
+                tokens.push(match ch {
+                    '(' => Token::LParen,
+                    _ => Token::RParen,
+                });
+            }
+            '>' | '<' => {
+                // An unquoted number right before the operator is the fd, as in `2>`
+                let fd = if !word.is_empty() && word.chars().all(|c| c.is_ascii_digit()) {
+                    word.parse().ok()
+                } else {
+                    None
+                };
+                if fd.is_some() {
+                    word.clear();
+                }
+                end_word(&mut tokens, &mut word);
+
+                let kind = match (ch, chars.get(i + 1)) {
+                    ('>', Some('>')) => {
+                        i += 1;
+                        RedirectKind::Append
+                    }
+                    ('>', Some('|')) => {
+                        i += 1;
+                        RedirectKind::Output
+                    }
+                    (_, Some('&')) => {
+                        i += 1;
+                        RedirectKind::Duplicate
+                    }
+                    ('>', _) => RedirectKind::Output,
+                    _ => RedirectKind::Input,
+                };
+                tokens.push(Token::Redirect {
+                    fd: fd.unwrap_or(if ch == '<' { 0 } else { 1 }),
+                    kind,
+                });
+            }
+            _ => word.push(ch),
+        }
+        i += 1;
+    }
+
+    end_word(&mut tokens, &mut word);
+    Ok(tokens)
+}
+
+/// Error for input ending inside quotes or right after a backslash.
+fn unterminated() -> ParseError {
+    ParseError::Incomplete("Syntax error: Unterminated quoted string".to_string())
+}
+
+/// Copy the double-quoted string starting at `start` into `word`.
+///
+/// # Returns
+/// - index of the closing quote.
+fn double_quote(chars: &[char], start: usize, word: &mut String) -> Result<usize, ParseError> {
+    word.push('"');
+    let mut i = start + 1;
+    while i < chars.len() {
+        let ch = chars[i];
+        match ch {
+            '"' => {
+                word.push(ch);
+                return Ok(i);
+            }
+            '\\' => match chars.get(i + 1) {
+                Some('\n') => i += 1,
+                Some(&next) => {
+                    word.push(ch);
+                    word.push(next);
+                    i += 1;
+                }
+                None => return Err(unterminated()),
+            },
+            '$' => i = dollar(chars, i, word)?,
+            '`' => i = backquote(chars, i, word)?,
+            _ => word.push(ch),
+        }
+        i += 1;
+    }
+    Err(unterminated())
+}
+
+/// Copy the `$` expression starting at `start` into `word`, keeping a
+/// `${...}` or `$(...)` in one piece even when it holds spaces or quotes.
+///
+/// # Returns
+/// - index of the last character of the expression.
+fn dollar(chars: &[char], start: usize, word: &mut String) -> Result<usize, ParseError> {
+    let (end, missing) = match chars.get(start + 1) {
+        Some('{') => (matching_brace(chars, start + 1), "Syntax error: Missing '}'"),
+        Some('(') => (matching_paren(chars, start + 1), "Syntax error: Missing ')'"),
+        _ => {
+            word.push('$');
+            return Ok(start);
+        }
+    };
+    match end {
+        Some(end) => {
+            word.extend(&chars[start..=end]);
+            Ok(end)
+        }
+        None => Err(ParseError::Incomplete(missing.to_string())),
+    }
+}
+
+/// Copy the backquoted command starting at `start` into `word`.
+///
+/// # Returns
+/// - index of the closing backquote.
+fn backquote(chars: &[char], start: usize, word: &mut String) -> Result<usize, ParseError> {
+    match matching_backquote(chars, start) {
+        Some(end) => {
+            word.extend(&chars[start..=end]);
+            Ok(end)
+        }
+        None => Err(ParseError::Incomplete(
+            "Syntax error: EOF in backquote substitution".to_string(),
+        )),
+    }
+}
+
+/// Find the `)` closing the `(` found at `start`, skipping nested
+/// parentheses, quoted text and backquoted commands. An unmatched `)` only
+/// closes the `(` once the text in between parses as whole commands, so
+/// the `)` ending a `case` pattern is skipped.
+///
+/// # Returns
+/// - index of the closing parenthesis, or `None` when the input ends first.
+///   When no text in between parses, the first `)` after invalid commands
+///   is returned so that running them reports the syntax error.
+pub(crate) fn matching_paren(chars: &[char], start: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut invalid = None;
+    let mut i = start;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '\'' => i += chars[i + 1..].iter().position(|&c| c == '\'')? + 1,
+            '"' => {
+                let mut word = String::new();
+                i = double_quote(chars, i, &mut word).ok()?;
+            }
+            '`' => i = matching_backquote(chars, i)?,
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth <= 0 {
+                    let body = chars[start + 1..i].iter().collect::<String>();
+                    match body.custom_split() {
+                        Ok(_) => return Some(i),
+                        Err(ParseError::Syntax(_)) => {
+                            invalid.get_or_insert(i);
+                        }
+                        Err(ParseError::Incomplete(_)) => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    invalid
+}
+
+/// Find the backquote closing the one found at `start`, skipping escaped
+/// characters.
+///
+/// # Returns
+/// - index of the closing backquote, or `None` when the input ends first.
+pub(crate) fn matching_backquote(chars: &[char], start: usize) -> Option<usize> {
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '`' => return Some(i),
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Find the `}` closing the `{` found at `start`, skipping nested braces
+/// and quoted text.
+///
+/// # Returns
+/// - index of the closing brace, or `None` when the input ends first.
+pub(crate) fn matching_brace(chars: &[char], start: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+    let mut i = start;
+    while i < chars.len() {
+        let ch = chars[i];
+        match quote {
+            Some(q) if ch == q => quote = None,
+            Some('"') if ch == '\\' => i += 1,
+            Some(_) => {}
+            None => match ch {
+                '\\' => i += 1,
+                '\'' | '"' => quote = Some(ch),
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(i);
+                    }
+                }
+                _ => {}
+            },
+        }
+        i += 1;
+    }
+    None
+}
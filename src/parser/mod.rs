@@ -0,0 +1,399 @@
+pub use lexer::*;
+mod compound;
+mod display;
+pub mod lexer;
+
+/// Words that start or end compound commands when found where a command
+/// name is expected.
+pub const RESERVED_WORDS: &[&str] = &[
+    "!", "case", "do", "done", "elif", "else", "esac", "fi", "for", "function", "if", "in", "then",
+    "until", "while", "{", "}",
+];
+
+/// A simple command. Right after parsing the words are raw: quotes,
+/// backslashes and `$` expansions are still in place and are only resolved
+/// by `expand_command` when the command runs.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Command {
+    pub name: String,                       // The command name, e.g., "echo"
+    pub args: Vec<String>,                  // List of arguments
+    pub redirects: Vec<Redirect>,           // Redirections, applied from left to right
+    pub assignments: Vec<(String, String)>, // `NAME=value` words before the name
+}
+
+/// How a redirection rebinds its file descriptor.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RedirectKind {
+    Input,     // `<`: read from the target file
+    Output,    // `>` or `>|`: truncate or create the target file
+    Append,    // `>>`: append to the target file
+    Duplicate, // `>&` or `<&`: copy the target descriptor, e.g. `2>&1`
+}
+
+/// A single redirection such as `2>> err.log`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Redirect {
+    pub fd: i32,            // Descriptor being rebound (0, 1, 2, ...)
+    pub kind: RedirectKind, // What the operator does
+    pub target: String,     // File name, or descriptor number for `Duplicate`
+}
+
+impl Command {
+    /// Add a parsed token to the command. While the command has no name, a
+    /// `NAME=value` token is an assignment; otherwise the first token becomes
+    /// the `name` and the following ones are appended to `args`.
+    ///
+    /// # Parameters
+    /// - `word`: token to add to the command structure.
+    pub fn add_string(&mut self, word: &str) {
+        if word.is_empty() {
+            return;
+        }
+        if self.name.is_empty() {
+            if let Some((name, value)) = split_assignment(word) {
+                self.assignments.push((name.to_string(), value.to_string()));
+            } else {
+                self.name = word.to_string();
+            }
+        } else {
+            self.args.push(word.to_string());
+        }
+    }
+
+    /// Return `true` when nothing at all was parsed for this command.
+    pub fn is_empty(&self) -> bool {
+        self.name.is_empty() && self.redirects.is_empty() && self.assignments.is_empty()
+    }
+}
+
+/// Split a raw `NAME=value` word into its name and raw value.
+///
+/// # Returns
+/// - `None` when the part before `=` is not a valid variable name.
+pub fn split_assignment(word: &str) -> Option<(&str, &str)> {
+    let (name, value) = word.split_once('=')?;
+    if is_name(name) {
+        Some((name, value))
+    } else {
+        None
+    }
+}
+
+/// Return `true` if `name` is a valid variable name: letters, digits and
+/// `_`, not starting with a digit.
+pub fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// A compound command, made of lists of commands run by the shell itself.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Compound {
+    /// `if` and `elif` conditions, each with the body run when it succeeds,
+    /// then the `else` body.
+    If {
+        branches: Vec<(List, List)>,
+        otherwise: Option<List>,
+    },
+    /// `while` loop, or `until` loop when `until` is set: the body runs as
+    /// long as the condition succeeds (fails).
+    While {
+        condition: List,
+        body: List,
+        until: bool,
+    },
+    /// `for name in words`: the raw words are expanded when the loop
+    /// starts, and the loop goes over `"$@"` when there is no `in`.
+    For {
+        name: String,
+        words: Option<Vec<String>>,
+        body: List,
+    },
+    /// `case word in pattern) body ;; ... esac`.
+    Case {
+        word: String,
+        items: Vec<CaseItem>,
+    },
+    Group(List),    // `{ list; }`: run in the current shell
+    Subshell(List), // `( list )`: run in a forked copy of the shell
+}
+
+/// An item of a `case` command: its body runs when the word matches one
+/// of the patterns.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CaseItem {
+    pub patterns: Vec<String>, // Raw patterns, written separated by `|`
+    pub body: List,
+}
+
+/// A function definition, `name() compound` or `function name compound`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Function {
+    pub name: String,
+    pub body: Compound,
+    pub redirects: Vec<Redirect>, // Applied to the body every time it runs
+}
+
+/// Return `true` if `name` can name a function: letters, digits, `_` and
+/// `-`, not starting with a digit.
+pub fn is_function_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '-')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// A command of a pipeline.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Stage {
+    Simple(Command),
+    /// A compound command and the redirections applied to the whole of
+    /// it, as in `while read line; do ...; done < file`.
+    Compound(Compound, Vec<Redirect>),
+    /// A function definition, run when the function is called.
+    Function(Function),
+}
+
+/// A sequence of commands joined with `|`: the stdout of each command is
+/// connected to the stdin of the next one.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Pipeline {
+    pub commands: Vec<Stage>,
+    pub negated: bool, // `!` before the pipeline inverts its status
+}
+
+/// Operator joining two pipelines of an and-or list.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Connector {
+    And, // `&&`: run the next pipeline when the previous one succeeded
+    Or,  // `||`: run the next pipeline when the previous one failed
+}
+
+/// Pipelines joined with `&&` and `||`, evaluated from left to right.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct AndOr {
+    pub first: Pipeline,
+    pub rest: Vec<(Connector, Pipeline)>,
+    pub background: bool, // Ended with `&`: run as a job without waiting for it
+}
+
+/// And-or lists separated by `;`, `&` or newlines, run one after the other.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct List {
+    pub items: Vec<AndOr>,
+}
+
+impl List {
+    /// Return `true` when the input held no command at all.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+/// Why an input could not be parsed.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParseError {
+    /// The input stops in the middle of a command, such as after a `|` or
+    /// inside quotes; more lines may complete it. The message is reported
+    /// when the input ends there.
+    Incomplete(String),
+    /// The input is invalid whatever follows.
+    Syntax(String),
+}
+
+pub trait CostumSplit {
+    /// Parse the string into a `List` of commands.
+    ///
+    /// # Returns
+    /// - the parsed `List`, or a `ParseError` telling whether more input is
+    ///   needed or the input is invalid.
+    fn custom_split(&self) -> Result<List, ParseError>;
+}
+
+impl CostumSplit for String {
+    /// Tokenize the string with `tokenize`, then build the `List` of and-or
+    /// lists, pipelines and commands it describes.
+    ///
+    /// # Returns
+    /// - the parsed `List`; `ParseError::Incomplete` for an unterminated
+    ///   quote, an open backslash, an unclosed `${`, `$(` or backquote, a
+    ///   trailing `|`, `&&` or `||`, or an unfinished compound command;
+    ///   `ParseError::Syntax` for a misplaced operator or reserved word.
+    fn custom_split(&self) -> Result<List, ParseError> {
+        let tokens = tokenize(self)?;
+        Parser { tokens, pos: 0 }.list()
+    }
+}
+
+/// Recursive descent parser over the tokens of an input.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    /// Return the current token when it is a word.
+    fn peek_word(&self) -> Option<&str> {
+        match self.peek() {
+            Some(Token::Word(word)) => Some(word),
+            _ => None,
+        }
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    /// Skip newlines, allowed after operators that need a following command.
+    fn skip_newlines(&mut self) {
+        while self.peek() == Some(&Token::Newline) {
+            self.pos += 1;
+        }
+    }
+
+    /// Error for `token` found where it is not allowed.
+    fn unexpected(token: &Token) -> ParseError {
+        match token {
+            Token::Newline => ParseError::Syntax("Syntax error: newline unexpected".to_string()),
+            _ => ParseError::Syntax(format!("Syntax error: \"{}\" unexpected", token.text())),
+        }
+    }
+
+    /// Error for an operator left without the command it needs.
+    fn end_of_file() -> ParseError {
+        ParseError::Incomplete("Syntax error: end of file unexpected".to_string())
+    }
+
+    /// `list := and_or ((";" | "&" | newline) and_or)*`, up to the end of
+    /// input.
+    fn list(&mut self) -> Result<List, ParseError> {
+        let mut list = List::default();
+        loop {
+            self.skip_newlines();
+            if self.peek().is_none() {
+                return Ok(list);
+            }
+            let and_or = self.and_or()?;
+            let background = and_or.background;
+            list.items.push(and_or);
+            match self.peek() {
+                None => return Ok(list),
+                Some(Token::Newline) => self.pos += 1,
+                Some(Token::Semi) if !background => self.pos += 1,
+                // `&` already separates it from the next command
+                _ if background => {}
+                Some(token) => return Err(Self::unexpected(token)),
+            }
+        }
+    }
+
+    /// `and_or := pipeline (("&&" | "||") newline* pipeline)* "&"?`
+    fn and_or(&mut self) -> Result<AndOr, ParseError> {
+        let mut and_or = AndOr {
+            first: self.pipeline()?,
+            ..AndOr::default()
+        };
+        loop {
+            let connector = match self.peek() {
+                Some(Token::AndIf) => Connector::And,
+                Some(Token::OrIf) => Connector::Or,
+                Some(Token::Amp) => {
+                    self.pos += 1;
+                    and_or.background = true;
+                    return Ok(and_or);
+                }
+                _ => return Ok(and_or),
+            };
+            self.pos += 1;
+            self.skip_newlines();
+            if self.peek().is_none() {
+                return Err(Self::end_of_file());
+            }
+            and_or.rest.push((connector, self.pipeline()?));
+        }
+    }
+
+    /// `pipeline := "!"? command ("|" newline* command)*`
+    fn pipeline(&mut self) -> Result<Pipeline, ParseError> {
+        let negated = self.peek_word() == Some("!");
+        if negated {
+            self.pos += 1;
+        }
+        let mut pipeline = Pipeline {
+            commands: vec![self.command()?],
+            negated,
+        };
+        while self.peek() == Some(&Token::Pipe) {
+            self.pos += 1;
+            self.skip_newlines();
+            if self.peek().is_none() {
+                return Err(Self::end_of_file());
+            }
+            pipeline.commands.push(self.command()?);
+        }
+        Ok(pipeline)
+    }
+
+    /// `command := function | compound redirect* | simple_command`
+    fn command(&mut self) -> Result<Stage, ParseError> {
+        if let Some(function) = self.function()? {
+            return Ok(Stage::Function(function));
+        }
+        match self.compound()? {
+            Some(compound) => Ok(Stage::Compound(compound, self.redirects()?)),
+            None => Ok(Stage::Simple(self.simple_command()?)),
+        }
+    }
+
+    /// Parse the redirections following a compound command.
+    fn redirects(&mut self) -> Result<Vec<Redirect>, ParseError> {
+        let mut redirects = Vec::new();
+        while let Some(&Token::Redirect { fd, kind }) = self.peek() {
+            self.pos += 1;
+            redirects.push(self.redirect(fd, kind)?);
+        }
+        Ok(redirects)
+    }
+
+    /// Parse the target of a redirection whose operator was just read.
+    fn redirect(&mut self, fd: i32, kind: RedirectKind) -> Result<Redirect, ParseError> {
+        let Some(Token::Word(target)) = self.next() else {
+            let message = "Syntax error: missing redirection target";
+            return Err(ParseError::Syntax(message.to_string()));
+        };
+        Ok(Redirect { fd, kind, target })
+    }
+
+    /// `simple_command := (word | redirect word)+`, not starting with a
+    /// reserved word.
+    fn simple_command(&mut self) -> Result<Command, ParseError> {
+        let mut command = Command::default();
+        loop {
+            match self.peek() {
+                Some(token @ Token::Word(word))
+                    if command.is_empty() && RESERVED_WORDS.contains(&word.as_str()) =>
+                {
+                    return Err(Self::unexpected(token));
+                }
+                Some(Token::Word(word)) => {
+                    command.add_string(word);
+                    self.pos += 1;
+                }
+                Some(&Token::Redirect { fd, kind }) => {
+                    self.pos += 1;
+                    let redirect = self.redirect(fd, kind)?;
+                    command.redirects.push(redirect);
+                }
+                Some(token) if command.is_empty() => return Err(Self::unexpected(token)),
+                None if command.is_empty() => return Err(Self::end_of_file()),
+                _ => return Ok(command),
+            }
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use crate::*;
+use chrono::Local;
+use std::ffi::CStr;
+
+/// Prompt used when `PS1` is not set: the working directory in colour.
+pub const DEFAULT_PS1: &str = "\\[\\e[1;33m\\]➜  \\[\\e[1;36m\\]\\w \\[\\e[33m\\]\\$ \\[\\e[0m\\]";
+
+/// Prompt used when `PS2` is not set, for the lines continuing a command.
+pub const DEFAULT_PS2: &str = "\\[\\e[33m\\]> \\[\\e[0m\\]";
+
+/// Return the working directory of the shell with the home directory
+/// written `~`.
+pub fn abbreviate_home(state: &ShellState) -> String {
+    match state.current_dir.strip_prefix(&state.home) {
+        Ok(p) if p.as_os_str().is_empty() => "~".to_string(),
+        Ok(p) => format!("~/{}", p.display()),
+        Err(_) => state.current_dir.display().to_string(),
+    }
+}
+
+/// Return the name of the user running the shell, from `$USER` or else
+/// the password database.
+fn user_name(state: &ShellState) -> String {
+    if let Some(user) = state.get_var("USER") {
+        return user.to_string();
+    }
+    unsafe {
+        let passwd = libc::getpwuid(libc::geteuid());
+        if passwd.is_null() {
+            return String::new();
+        }
+        CStr::from_ptr((*passwd).pw_name)
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+/// Return the host name up to its first `.`.
+fn host_name() -> String {
+    let mut buf = [0u8; 256];
+    if unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) } == -1 {
+        return String::new();
+    }
+    let name = CStr::from_bytes_until_nul(&buf).map_or("".into(), CStr::to_string_lossy);
+    name.split('.').next().unwrap_or("").to_string()
+}
+
+/// Expand the escapes of a prompt string such as `PS1`.
+///
+/// `\u` is the user name, `\h` the host name, `\w` the working directory
+/// and `\W` its last component, both with the home directory written `~`.
+/// `\$` is `#` for root and `$` otherwise, `\t` the time as `HH:MM:SS` and
+/// `\?` the status of the last command. `\[` and `\]` surround text taking
+/// no room on the screen, such as colours, turned into the `\x01` and
+/// `\x02` markers the line editor skips. `\e` is the escape character, `\n`
+/// a newline and `\\` a backslash; other escapes are left as they are.
+///
+/// # Returns
+/// - the prompt to print.
+pub fn expand_prompt(text: &str, state: &ShellState) -> String {
+    let mut prompt = String::new();
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            prompt.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('u') => prompt.push_str(&user_name(state)),
+            Some('h') => prompt.push_str(&host_name()),
+            Some('w') => prompt.push_str(&abbreviate_home(state)),
+            Some('W') => {
+                let dir = abbreviate_home(state);
+                let name = match dir.rsplit_once('/') {
+                    Some((_, "")) | None => &dir,
+                    Some((_, name)) => name,
+                };
+                prompt.push_str(name);
+            }
+            Some('$') => {
+                let root = unsafe { libc::geteuid() } == 0;
+                prompt.push(if root { '#' } else { '$' });
+            }
+            Some('t') => prompt.push_str(&Local::now().format("%H:%M:%S").to_string()),
+            Some('?') => prompt.push_str(&state.last_command_staus.to_string()),
+            Some('[') => prompt.push('\x01'),
+            Some(']') => prompt.push('\x02'),
+            Some('e') => prompt.push('\x1b'),
+            Some('n') => prompt.push('\n'),
+            Some('\\') => prompt.push('\\'),
+            Some(other) => {
+                prompt.push('\\');
+                prompt.push(other);
+            }
+            None => prompt.push('\\'),
+        }
+    }
+    prompt
+}
+
+/// Run the commands of `PROMPT_COMMAND` before the prompt is printed,
+/// leaving `$?` as it was for the prompt.
+pub fn run_prompt_command(state: &mut ShellState) {
+    let Some(command) = state.get_var("PROMPT_COMMAND").map(str::to_string) else {
+        return;
+    };
+    let status = state.last_command_staus;
+    run_script(&command, "PROMPT_COMMAND", state);
+    take_interrupt();
+    state.last_command_staus = status;
+}
@@ -1,13 +1,27 @@
-use std::path::PathBuf;
+use crate::{Builtin, ShellState, Streams};
+use std::io::Write;
+use std::path::Path;
 
 /// Print the current working directory.
 ///
 /// # Parameters
 /// - `current_dir`: the path to print.
+/// - `stdout`: stream the path is written to.
 ///
 /// # Returns
-/// - `0` always.
-pub fn pwd(current_dir: &PathBuf) -> i32 {
-    println!("\x1b[1;34m{}\x1b[0m", current_dir.display());
-    0
+/// - `0` on success, `1` if writing fails.
+pub fn pwd(current_dir: &Path, stdout: &mut dyn Write) -> i32 {
+    match writeln!(stdout, "{}", current_dir.display()) {
+        Ok(_) => 0,
+        Err(_) => 1,
+    }
+}
+
+/// `pwd` builtin, see [`pwd`].
+pub struct PwdBuiltin;
+
+impl Builtin for PwdBuiltin {
+    fn run(&self, _args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        pwd(&state.current_dir, io.stdout)
+    }
 }
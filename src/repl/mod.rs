@@ -0,0 +1,112 @@
+use crate::*;
+use std::io;
+use std::io::ErrorKind;
+
+/// Apply history expansion to the line of `entry` starting at `start`,
+/// printing the line when it changed so the user sees what runs.
+///
+/// # Returns
+/// - `false` when the expansion failed; the error is reported and `$?` set.
+fn expand_line(entry: &mut String, start: usize, state: &mut ShellState) -> bool {
+    match expand_history(&entry[start..], state.hist.entries()) {
+        Ok(Some(line)) => {
+            print!("{}", line);
+            entry.replace_range(start.., &line);
+            true
+        }
+        Ok(None) => true,
+        Err(err) => {
+            print_error(&err);
+            state.last_command_staus = 1;
+            false
+        }
+    }
+}
+
+/// Run the interactive loop: print the prompt, read input, parse and
+/// execute commands until end of file. The prompt is `$PS1` and the one of
+/// the lines continuing a command `$PS2`, see [`expand_prompt`], printed
+/// after running `$PROMPT_COMMAND`.
+///
+/// # Parameters
+/// - `state`: shell state used and updated by every command.
+///
+/// # Returns
+/// - `Ok(())` on clean exit, or an `io::Error` if reading stdin or writing stdout fails.
+pub fn repl(state: &mut ShellState) -> io::Result<()> {
+    load_history(state);
+    let mut editor = Editor::new();
+    loop {
+        notify_jobs(state);
+        run_traps(state);
+        run_prompt_command(state);
+        let ps1 = state.get_var("PS1").unwrap_or(DEFAULT_PS1);
+        let prompt = expand_prompt(ps1, state);
+        let mut entry = String::new();
+        let size = match editor.read_line(&prompt, state, &mut entry) {
+            Ok(size) => size,
+            Err(err) if err.kind() == ErrorKind::Interrupted => {
+                state.last_command_staus = 130;
+                run_trap(libc::SIGINT, state);
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        if size == 0 {
+            println!();
+            exit_shell(state.last_command_staus, state);
+        }
+        if !expand_line(&mut entry, 0, state) {
+            continue;
+        }
+
+        let list = loop {
+            match entry.custom_split() {
+                Ok(list) => break Some(list),
+                Err(ParseError::Incomplete(err)) => {
+                    let start = entry.len();
+                    let ps2 = state.get_var("PS2").unwrap_or(DEFAULT_PS2);
+                    let prompt = expand_prompt(ps2, state);
+                    let size = match editor.read_line(&prompt, state, &mut entry) {
+                        Ok(size) => size,
+                        Err(err) if err.kind() == ErrorKind::Interrupted => {
+                            state.last_command_staus = 130;
+                            run_trap(libc::SIGINT, state);
+                            break None;
+                        }
+                        Err(err) => return Err(err),
+                    };
+                    if size == 0 {
+                        print_error(&err);
+                        state.last_command_staus = 2;
+                        break None;
+                    }
+                    if !expand_line(&mut entry, start, state) {
+                        break None;
+                    }
+                }
+                Err(ParseError::Syntax(err)) => {
+                    print_error(&err);
+                    state.last_command_staus = 2;
+                    break None;
+                }
+            }
+        };
+        let Some(list) = list else {
+            continue;
+        };
+        if list.is_empty() {
+            continue;
+        }
+
+        // Recorded first, so `history` lists the running line and `exit` is kept
+        record_history(state, &entry);
+        take_interrupt();
+        exec_list(&list, state);
+        if take_interrupt() {
+            // The prompt starts on a new line, after the `^C` echoed
+            println!();
+            state.last_command_staus = 130;
+        }
+    }
+}
@@ -1,17 +1,43 @@
 use std::fs;
-use std::path::PathBuf;
+use std::io;
+use std::io::ErrorKind;
+use std::io::Write;
+use std::path::Path;
 
-use crate::print_error;
+use crate::{Builtin, ShellState, Streams, interrupted, write_error};
+
+/// Remove a directory and everything inside it, checking for `Ctrl+C`
+/// before every entry. Symbolic links are removed, not followed.
+///
+/// # Returns
+/// - `Ok(())` once removed, an `Interrupted` error when stopped by
+///   `Ctrl+C`, or the first error met.
+fn remove_tree(dir: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        if interrupted() {
+            return Err(io::Error::from(ErrorKind::Interrupted));
+        }
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            remove_tree(&entry.path())?;
+        } else {
+            fs::remove_file(entry.path())?;
+        }
+    }
+    fs::remove_dir(dir)
+}
 
 /// Remove files or directories.
 ///
 /// # Parameters
 /// - `args`: arguments, may include `-r` for recursive removal and paths.
 /// - `current_dir`: base directory to resolve relative paths.
+/// - `stderr`: stream errors are written to.
 ///
 /// # Returns
-/// - `0` on success, non-zero on errors.
-pub fn rm(args: &[String], current_dir: &PathBuf) -> i32 {
+/// - `0` on success, `130` when interrupted with `Ctrl+C`, non-zero on
+///   errors.
+pub fn rm(args: &[String], current_dir: &Path, stderr: &mut dyn Write) -> i32 {
     let mut recursive = false;
     let mut paths = vec![];
     // Parse arguments to separate flags and paths
@@ -24,12 +50,12 @@ pub fn rm(args: &[String], current_dir: &PathBuf) -> i32 {
     }
     // Check if any paths were provided
     if paths.is_empty() {
-        print_error("rm: missing operand");
+        write_error(stderr, "rm: missing operand");
         return 1;
     }
 
     for arg in paths {
-        let mut tmp = current_dir.clone();
+        let mut tmp = current_dir.to_path_buf();
 
         let mut valid = false;
         for part in arg.split('/') {
@@ -45,22 +71,37 @@ pub fn rm(args: &[String], current_dir: &PathBuf) -> i32 {
             }
         }
         if !valid {
-            print_error(&format!("rm: refusing to remove '.' or '..' directory: skipping '{arg}'"));
+            write_error(stderr, &format!("rm: refusing to remove '.' or '..' directory: skipping '{arg}'"));
             continue;
         }
         if tmp.is_dir() {
             if recursive {
-                if let Err(err) = fs::remove_dir_all(&tmp) {
-                    print_error(&format!("{arg}: {err}"));
+                match remove_tree(&tmp) {
+                    Ok(()) => {}
+                    Err(_) if interrupted() => return 130,
+                    Err(err) => write_error(stderr, &format!("{arg}: {err}")),
                 }
             } else {
-                print_error(&format!("rm: cannot remove '{arg}': Is a directory"));
+                write_error(stderr, &format!("rm: cannot remove '{arg}': Is a directory"));
             }
         } else {
             if let Err(err) = fs::remove_file(&tmp) {
-                print_error(&format!("{arg}: {err}"));
+                write_error(stderr, &format!("{arg}: {err}"));
             }
         }
     }
     0
-}
\ No newline at end of file
+}
+
+/// `rm` builtin, see [`rm`].
+pub struct RmBuiltin;
+
+impl Builtin for RmBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        rm(args, &state.current_dir, io.stderr)
+    }
+
+    fn flags(&self) -> &str {
+        "r"
+    }
+}
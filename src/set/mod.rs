@@ -0,0 +1,111 @@
+use crate::{Builtin, Options, ShellState, Streams, quote, write_error};
+use std::io::Write;
+
+/// List every shell variable, change options or replace the positional
+/// parameters.
+///
+/// # Parameters
+/// - `args`: `-o NAME` / `+o NAME` to turn an option on / off (alone, they
+///   list the options), or its letter such as `-e` / `+e` for `errexit` and
+///   `-x` / `+x` for `xtrace`, then the new positional parameters, optionally
+///   preceded by `--`; no argument lists the variables as `NAME=value`.
+/// - `state`: shell state holding the variables and parameters.
+/// - `stdout`: stream the listing is written to.
+/// - `stderr`: stream errors are written to.
+///
+/// # Returns
+/// - `0` on success, `2` for an unknown option.
+pub fn set(
+    args: &[String],
+    state: &mut ShellState,
+    stdout: &mut dyn Write,
+    stderr: &mut dyn Write,
+) -> i32 {
+    if args.is_empty() {
+        let mut names: Vec<&String> = state.vars.keys().collect();
+        names.sort();
+        for name in names {
+            let value = &state.vars[name].value;
+            if writeln!(stdout, "{}={}", name, quote(value)).is_err() {
+                return 1;
+            }
+        }
+        return 0;
+    }
+
+    let mut i = 0;
+    let mut end_of_options = false;
+    while let Some(arg) = args.get(i) {
+        match arg.as_str() {
+            "--" => {
+                i += 1;
+                end_of_options = true;
+                break;
+            }
+            "-o" | "+o" => {
+                let on = arg == "-o";
+                let Some(name) = args.get(i + 1) else {
+                    return list_options(state, on, stdout);
+                };
+                match state.options.get_mut(name) {
+                    Some(option) => *option = on,
+                    None => {
+                        write_error(stderr, &format!("set: {}: invalid option name", name));
+                        return 2;
+                    }
+                }
+                i += 2;
+            }
+            arg if (arg.starts_with('-') || arg.starts_with('+')) && arg.len() > 1 => {
+                let on = arg.starts_with('-');
+                for letter in arg.chars().skip(1) {
+                    let name = Options::LETTERS.iter().find(|(l, _)| *l == letter);
+                    match name.and_then(|(_, name)| state.options.get_mut(name)) {
+                        Some(option) => *option = on,
+                        None => {
+                            write_error(stderr, &format!("set: {}: invalid option", arg));
+                            return 2;
+                        }
+                    }
+                }
+                i += 1;
+            }
+            _ => break,
+        }
+    }
+    // Positional parameters are only replaced when some are given, or after `--`
+    if i < args.len() || end_of_options {
+        state.positional = args[i..].to_vec();
+    }
+    0
+}
+
+/// List the options: `name on|off` lines for `set -o`, or commands
+/// restoring them for `set +o`.
+fn list_options(state: &ShellState, human: bool, stdout: &mut dyn Write) -> i32 {
+    for name in Options::NAMES {
+        let on = state.options.get(name).unwrap_or(false);
+        let res = if human {
+            writeln!(stdout, "{:<15}{}", name, if on { "on" } else { "off" })
+        } else {
+            writeln!(stdout, "set {}o {}", if on { '-' } else { '+' }, name)
+        };
+        if res.is_err() {
+            return 1;
+        }
+    }
+    0
+}
+
+/// `set` builtin, see [`set`].
+pub struct SetBuiltin;
+
+impl Builtin for SetBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        set(args, state, io.stdout, io.stderr)
+    }
+
+    fn flags(&self) -> &str {
+        "oex"
+    }
+}
@@ -0,0 +1,160 @@
+use crate::{
+    Builtin, CostumSplit, Flow, ParseError, ShellState, Streams, exec_list, interrupted,
+    print_error, write_error,
+};
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Startup file run by every interactive shell.
+const SYSTEM_RC: &str = "/etc/0shellrc";
+
+/// Startup file of the user, in the home directory, run after `SYSTEM_RC`.
+const USER_RC: &str = ".0shellrc";
+
+/// Run the commands of a script in the current shell, one complete command
+/// at a time, so a command may use what the previous ones defined.
+///
+/// # Parameters
+/// - `source`: text of the script.
+/// - `name`: name of the script, used in syntax error messages.
+/// - `state`: shell state the commands run in.
+///
+/// # Returns
+/// - exit status of the last command run, `0` when there is none, or `2`
+///   when the script has a syntax error, which stops it.
+pub fn run_script(source: &str, name: &str, state: &mut ShellState) -> i32 {
+    let mut lines = source.split_inclusive('\n');
+    let next_line = |buf: &mut String| {
+        let line = lines.next().unwrap_or("");
+        buf.push_str(line);
+        Ok(line.len())
+    };
+    run_lines(next_line, name, state)
+}
+
+/// Run commands read one line at a time, like [`run_script`], but without
+/// reading ahead: used for a script piped to the shell, whose commands may
+/// read the rest of the input themselves. `Ctrl+C` stops the input with
+/// status `130`.
+///
+/// # Parameters
+/// - `read_line`: appends the next line to the buffer and returns its size
+///   in bytes, `0` at the end of the input.
+/// - `name`: name of the input, used in error messages.
+/// - `state`: shell state the commands run in.
+///
+/// # Returns
+/// - exit status of the last command run, `0` when there is none, or `2`
+///   on a syntax error or when the input cannot be read.
+pub fn run_lines(
+    mut read_line: impl FnMut(&mut String) -> io::Result<usize>,
+    name: &str,
+    state: &mut ShellState,
+) -> i32 {
+    let mut status = 0;
+    let mut entry = String::new();
+    let mut line_number = 0;
+    loop {
+        match read_line(&mut entry) {
+            Ok(0) => break,
+            Ok(_) => line_number += 1,
+            Err(err) => {
+                print_error(&format!("{}: {}", name, err));
+                state.last_command_staus = 2;
+                return 2;
+            }
+        }
+        match entry.custom_split() {
+            Ok(list) => {
+                entry.clear();
+                if !list.is_empty() {
+                    status = exec_list(&list, state);
+                }
+                // `return` in a sourced file leaves it, `Ctrl+C` any file
+                if state.flow == Some(Flow::Return) || interrupted() {
+                    return status;
+                }
+            }
+            Err(ParseError::Incomplete(_)) => {}
+            Err(ParseError::Syntax(err)) => {
+                print_error(&format!("{}: {}: {}", name, line_number, err));
+                state.last_command_staus = 2;
+                return 2;
+            }
+        }
+    }
+    if let Err(ParseError::Incomplete(err) | ParseError::Syntax(err)) = entry.custom_split() {
+        print_error(&format!("{}: {}: {}", name, line_number, err));
+        state.last_command_staus = 2;
+        return 2;
+    }
+    status
+}
+
+/// Run the startup files of an interactive shell: `/etc/0shellrc`, then
+/// `~/.0shellrc`. A missing file is skipped.
+pub fn run_startup_files(state: &mut ShellState) {
+    let home = match state.get_var("HOME") {
+        Some(home) => PathBuf::from(home),
+        None => state.home.clone(),
+    };
+    for path in [PathBuf::from(SYSTEM_RC), home.join(USER_RC)] {
+        if let Ok(source) = fs::read_to_string(&path) {
+            run_script(&source, &path.display().to_string(), state);
+        }
+    }
+}
+
+/// Run the commands of a file in the current shell, so the directory and
+/// variables it changes stay changed. `return` leaves the file early.
+///
+/// # Parameters
+/// - `args`: the file, relative to the current directory, then the
+///   positional parameters to run it with, the current ones by default.
+/// - `state`: shell state the commands run in.
+/// - `stderr`: stream errors are written to.
+///
+/// # Returns
+/// - exit status of the last command of the file or the status given to
+///   `return`, `1` when it cannot be read, `2` without file name.
+pub fn source(args: &[String], state: &mut ShellState, stderr: &mut dyn Write) -> i32 {
+    let Some(file) = args.first() else {
+        write_error(stderr, "source: filename argument required");
+        write_error(stderr, "source: usage: source filename [arguments]");
+        return 2;
+    };
+    let source = match fs::read_to_string(state.current_dir.join(file)) {
+        Ok(source) => source,
+        Err(err) => {
+            write_error(stderr, &format!("source: {}: {}", file, err));
+            return 1;
+        }
+    };
+
+    let positional = if args.len() > 1 {
+        Some(std::mem::replace(&mut state.positional, args[1..].to_vec()))
+    } else {
+        None
+    };
+    state.source_depth += 1;
+    let status = run_script(&source, file, state);
+    state.source_depth -= 1;
+    if state.flow == Some(Flow::Return) {
+        state.flow = None;
+    }
+    if let Some(positional) = positional {
+        state.positional = positional;
+    }
+    status
+}
+
+/// `source` and `.` builtin, see [`source`].
+pub struct SourceBuiltin;
+
+impl Builtin for SourceBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        source(args, state, io.stderr)
+    }
+}
@@ -0,0 +1,222 @@
+use crate::*;
+use std::collections::HashMap;
+use std::env;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// A shell variable and whether it is passed to the environment of the
+/// programs the shell runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variable {
+    pub value: String,
+    pub exported: bool,
+}
+
+/// Options changed with `set -o NAME` and `set +o NAME`.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    pub nullglob: bool, // A pattern matching no file expands to nothing
+    pub failglob: bool, // A pattern matching no file is an error
+    pub globstar: bool, // `**` matches any number of directories
+    pub errexit: bool,  // Exit as soon as a command fails, `set -e`
+    pub xtrace: bool,   // Print every command before running it, `set -x`
+}
+
+impl Options {
+    /// Names of every option, in the order `set -o` lists them.
+    pub const NAMES: &'static [&'static str] =
+        &["errexit", "failglob", "globstar", "nullglob", "xtrace"];
+
+    /// Options that also have a single-letter form, as in `set -e`.
+    pub const LETTERS: &'static [(char, &'static str)] = &[('e', "errexit"), ('x', "xtrace")];
+
+    /// Return the flag of the option `name`, if there is such an option.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "errexit" => Some(&mut self.errexit),
+            "failglob" => Some(&mut self.failglob),
+            "globstar" => Some(&mut self.globstar),
+            "nullglob" => Some(&mut self.nullglob),
+            "xtrace" => Some(&mut self.xtrace),
+            _ => None,
+        }
+    }
+
+    /// Return whether the option `name` is on, if there is such an option.
+    pub fn get(&self, name: &str) -> Option<bool> {
+        match name {
+            "errexit" => Some(self.errexit),
+            "failglob" => Some(self.failglob),
+            "globstar" => Some(self.globstar),
+            "nullglob" => Some(self.nullglob),
+            "xtrace" => Some(self.xtrace),
+            _ => None,
+        }
+    }
+
+    /// Return the letters of the options that are on, for `$-`.
+    pub fn letters(&self) -> String {
+        Self::LETTERS
+            .iter()
+            .filter(|(_, name)| self.get(name) == Some(true))
+            .map(|(letter, _)| *letter)
+            .collect()
+    }
+}
+
+/// A jump out of the commands being run, requested by `break`, `continue`
+/// or `return` and carried out by the enclosing loops, function or file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flow {
+    Break(usize),    // Leave that many enclosing loops
+    Continue(usize), // Leave that many loops minus one, then go on with the next iteration
+    Return,          // Leave the function or sourced file being run
+}
+
+/// Everything a command may need to read or change in the running shell.
+pub struct ShellState {
+    pub current_dir: PathBuf,             // Working directory of the shell
+    pub history_current_dir: PathBuf,     // Previous directory, used by `cd -`
+    pub hist: History,                    // Commands entered, loaded from the history file
+    pub home: PathBuf,                    // User's home directory
+    pub last_command_staus: i32,          // Exit status of the last command, `$?`
+    pub builtins: Registry,               // Builtins available by name
+    pub vars: HashMap<String, Variable>,  // Shell and environment variables
+    pub positional: Vec<String>,          // Positional parameters `$1`, `$2`, ...
+    pub arg0: String,                     // Name of the shell or script, `$0`
+    pub pid: i32,                         // Process id of the shell, `$$`
+    pub options: Options,                 // Options set with `set -o`
+    pub substitution_status: Option<i32>, // Status of the last `$(...)`, for `a=$(cmd)`
+    pub completions: HashMap<String, CompletionSpec>, // Specs registered with `complete`
+    pub loop_depth: usize,                // Number of loops being run, for `break`
+    pub flow: Option<Flow>,               // Pending `break`, `continue` or `return`
+    pub in_condition: bool,               // Running a condition, where `set -e` is ignored
+    pub functions: HashMap<String, Rc<Function>>, // Functions defined by name
+    pub scopes: Vec<HashMap<String, Option<Variable>>>, // Values hidden by `local`, per call
+    pub source_depth: usize,              // Number of files being sourced, for `return`
+    pub aliases: HashMap<String, String>, // Aliases defined by name, with their raw text
+    pub aliases_in_use: Vec<String>,      // Aliases being expanded, not expanded again
+    pub jobs: Vec<Job>,                   // Background and stopped jobs, by number
+    pub job_control: bool,                // Jobs get process groups and the terminal
+    pub terminal_modes: Option<libc::termios>, // Terminal modes given back after a job
+    pub last_background_pid: Option<i32>, // Process id of the last `&` job, `$!`
+    pub traps: HashMap<i32, String>,      // Commands run on a signal by number, `0` on exit
+    pub running_trap: bool,               // Running a trap, which other signals wait for
+}
+
+impl ShellState {
+    /// Create the state of a shell started in the process working directory,
+    /// with the default builtins registered and the process environment
+    /// imported as exported variables.
+    pub fn new() -> Self {
+        let history_current_dir = env::current_dir().unwrap_or(PathBuf::from("/"));
+        let current_dir = history_current_dir.clone();
+        let home = match env::home_dir() {
+            Some(p) => p,
+            None => {
+                print_error("Impossible to get your home dir!");
+                current_dir.clone()
+            }
+        };
+        let vars = env::vars()
+            .map(|(name, value)| {
+                let var = Variable {
+                    value,
+                    exported: true,
+                };
+                (name, var)
+            })
+            .collect();
+        Self {
+            current_dir,
+            history_current_dir,
+            hist: History::default(),
+            home,
+            last_command_staus: 0,
+            builtins: Registry::with_defaults(),
+            vars,
+            positional: Vec::new(),
+            arg0: env::args().next().unwrap_or("0-shell".to_string()),
+            pid: std::process::id() as i32,
+            options: Options::default(),
+            substitution_status: None,
+            completions: HashMap::new(),
+            loop_depth: 0,
+            flow: None,
+            in_condition: false,
+            functions: HashMap::new(),
+            scopes: Vec::new(),
+            source_depth: 0,
+            aliases: HashMap::new(),
+            aliases_in_use: Vec::new(),
+            jobs: Vec::new(),
+            job_control: false,
+            terminal_modes: None,
+            last_background_pid: None,
+            traps: HashMap::new(),
+            running_trap: false,
+        }
+    }
+
+    /// Return `true` if `name` is run by the shell itself: a function or a
+    /// builtin.
+    pub fn is_internal(&self, name: &str) -> bool {
+        self.functions.contains_key(name) || self.builtins.contains(name)
+    }
+
+    /// Return the value of the variable `name`, if it is set.
+    pub fn get_var(&self, name: &str) -> Option<&str> {
+        self.vars.get(name).map(|var| var.value.as_str())
+    }
+
+    /// Set the variable `name`, keeping its exported flag when it exists.
+    pub fn set_var(&mut self, name: &str, value: &str) {
+        match self.vars.get_mut(name) {
+            Some(var) => var.value = value.to_string(),
+            None => {
+                let var = Variable {
+                    value: value.to_string(),
+                    exported: false,
+                };
+                self.vars.insert(name.to_string(), var);
+            }
+        }
+    }
+
+    /// Mark the variable `name` as exported, setting it first when a value
+    /// is given. Exporting an unset variable without value creates it empty.
+    pub fn export_var(&mut self, name: &str, value: Option<&str>) {
+        let var = self.vars.entry(name.to_string()).or_insert(Variable {
+            value: String::new(),
+            exported: true,
+        });
+        var.exported = true;
+        if let Some(value) = value {
+            var.value = value.to_string();
+        }
+    }
+
+    /// Remove the variable `name`.
+    pub fn unset_var(&mut self, name: &str) {
+        self.vars.remove(name);
+    }
+
+    /// Return the exported variables as sorted `NAME=value` strings, the
+    /// environment handed to external programs.
+    pub fn environ(&self) -> Vec<String> {
+        let mut env: Vec<String> = self
+            .vars
+            .iter()
+            .filter(|(_, var)| var.exported)
+            .map(|(name, var)| format!("{}={}", name, var.value))
+            .collect();
+        env.sort();
+        env
+    }
+}
+
+impl Default for ShellState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,266 @@
+use crate::*;
+use std::io;
+use std::io::Write;
+use std::io::stdout;
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Signals a trap can be set on, by name without `SIG`. `EXIT`, number
+/// `0`, stands for the shell exiting.
+const SIGNALS: &[(&str, libc::c_int)] = &[
+    ("EXIT", 0),
+    ("HUP", libc::SIGHUP),
+    ("INT", libc::SIGINT),
+    ("QUIT", libc::SIGQUIT),
+    ("USR1", libc::SIGUSR1),
+    ("USR2", libc::SIGUSR2),
+    ("ALRM", libc::SIGALRM),
+    ("TERM", libc::SIGTERM),
+];
+
+/// One bit per trapped signal that arrived and whose trap did not run yet.
+static PENDING: AtomicU64 = AtomicU64::new(0);
+
+/// Handler of the trapped signals but `SIGINT`, which keeps the handler of
+/// [`catch_interrupts`] and its flag.
+extern "C" fn on_signal(signal: libc::c_int) {
+    PENDING.fetch_or(1 << signal, Ordering::SeqCst);
+}
+
+/// Return the number of the signal `spec`, a name with or without `SIG` in
+/// any case, `EXIT` or a number.
+fn signal_number(spec: &str) -> Option<libc::c_int> {
+    let name = spec.to_ascii_uppercase();
+    let name = name.strip_prefix("SIG").unwrap_or(&name);
+    SIGNALS
+        .iter()
+        .find(|(n, number)| *n == name || number.to_string() == name)
+        .map(|(_, number)| *number)
+}
+
+/// Return the name `trap -p` prints for the signal `signal`.
+fn signal_name(signal: libc::c_int) -> String {
+    match SIGNALS.iter().find(|(_, number)| *number == signal) {
+        Some((name, 0)) => name.to_string(),
+        Some((name, _)) => format!("SIG{}", name),
+        None => signal.to_string(),
+    }
+}
+
+/// Set what the shell does on `signal`: run a trap, ignore it for an
+/// empty `action`, or take the default action back without one.
+fn set_disposition(signal: libc::c_int, action: Option<&str>) -> io::Result<()> {
+    if signal == 0 {
+        return Ok(());
+    }
+    let handler = match action {
+        Some("") => libc::SIG_IGN,
+        _ if signal == libc::SIGINT => return catch_interrupts(),
+        Some(_) => on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t,
+        None => libc::SIG_DFL,
+    };
+    unsafe {
+        let mut sigaction: libc::sigaction = std::mem::zeroed();
+        sigaction.sa_sigaction = handler;
+        sigaction.sa_flags = libc::SA_RESTART;
+        libc::sigemptyset(&mut sigaction.sa_mask);
+        if libc::sigaction(signal, &sigaction, std::ptr::null_mut()) == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Run the trap set on `signal`, see [`run_action`]. Nothing runs when
+/// there is no trap or the signal is ignored.
+pub fn run_trap(signal: libc::c_int, state: &mut ShellState) {
+    if let Some(action) = state.traps.get(&signal).cloned() {
+        run_action(&action, state);
+    }
+}
+
+/// Run the text of a trap as if it was typed, leaving `$?` as it was.
+fn run_action(action: &str, state: &mut ShellState) {
+    let list = match action.to_string().custom_split() {
+        Ok(list) => list,
+        Err(ParseError::Incomplete(err) | ParseError::Syntax(err)) => {
+            print_error(&format!("trap: {}", err));
+            return;
+        }
+    };
+    let status = state.last_command_staus;
+    let running_trap = state.running_trap;
+    state.running_trap = true;
+    exec_list(&list, state);
+    state.running_trap = running_trap;
+    state.last_command_staus = status;
+}
+
+/// Run the traps of the signals that arrived since the last call, between
+/// two commands. A trap on `SIGINT` takes the place of abandoning the rest
+/// of the commands, so the flag of `Ctrl+C` is cleared. Signals arriving
+/// while a trap runs wait for it to end.
+pub fn run_traps(state: &mut ShellState) {
+    if state.running_trap {
+        return;
+    }
+    loop {
+        let mut pending = PENDING.swap(0, Ordering::SeqCst);
+        if state.traps.contains_key(&libc::SIGINT) && take_interrupt() {
+            pending |= 1 << libc::SIGINT;
+        }
+        if pending == 0 {
+            return;
+        }
+        for (_, signal) in SIGNALS {
+            if pending & (1 << signal) != 0 {
+                run_trap(*signal, state);
+            }
+        }
+    }
+}
+
+/// Put the traps back to the default in a forked copy of the shell, which
+/// keeps the ignored signals only.
+pub fn reset_traps(state: &mut ShellState) {
+    PENDING.store(0, Ordering::SeqCst);
+    for (signal, action) in &state.traps {
+        let handler = if action.is_empty() {
+            libc::SIG_IGN
+        } else {
+            libc::SIG_DFL
+        };
+        if *signal != 0 {
+            unsafe { libc::signal(*signal, handler) };
+        }
+    }
+    state.traps.retain(|_, action| action.is_empty());
+}
+
+/// Exit the shell with `status`, running the trap on `EXIT` first. The
+/// trap runs once, so `exit` in it exits right away.
+pub fn exit_shell(status: i32, state: &mut ShellState) -> ! {
+    if let Some(action) = state.traps.remove(&0) {
+        take_interrupt();
+        state.last_command_staus = status;
+        run_action(&action, state);
+    }
+    let _ = stdout().flush();
+    process::exit(status)
+}
+
+/// Write the trap on `signal` as the command that sets it again.
+fn format_trap(signal: libc::c_int, action: &str) -> String {
+    format!("trap -- {} {}", quote(action), signal_name(signal))
+}
+
+/// Set, reset or print the commands run when the shell gets a signal or
+/// exits.
+///
+/// # Parameters
+/// - `args`: the commands then the signals to trap, as names, `EXIT` or
+///   numbers. An empty command ignores the signals, and `-` or no command
+///   before a single signal resets them. With no arguments, or `-p` and
+///   the signals to print, the traps are printed as commands.
+/// - `state`: shell state holding the traps.
+/// - `stdout`: stream the traps are printed to.
+/// - `stderr`: stream errors are written to.
+///
+/// # Returns
+/// - `0` on success, `1` when a signal is not known.
+pub fn trap(
+    args: &[String],
+    state: &mut ShellState,
+    stdout: &mut dyn Write,
+    stderr: &mut dyn Write,
+) -> i32 {
+    let args = match args.split_first() {
+        Some((flag, rest)) if flag == "--" => rest,
+        _ => args,
+    };
+    let (print, args) = match args.split_first() {
+        Some((flag, rest)) if flag == "-p" => (true, rest),
+        _ => (args.is_empty(), args),
+    };
+    let (action, specs) = match args {
+        _ if print => (None, args),
+        [_] => (None, args),
+        [action, specs @ ..] if action == "-" => (None, specs),
+        [action, specs @ ..] => (Some(action.as_str()), specs),
+        [] => (None, args),
+    };
+
+    let mut status = 0;
+    let mut signals = Vec::new();
+    for spec in specs {
+        match signal_number(spec) {
+            Some(signal) => signals.push(signal),
+            None => {
+                let message = format!("trap: {}: invalid signal specification", spec);
+                write_error(stderr, &message);
+                status = 1;
+            }
+        }
+    }
+    if print {
+        if specs.is_empty() {
+            signals = SIGNALS.iter().map(|(_, signal)| *signal).collect();
+        }
+        for signal in signals {
+            if let Some(action) = state.traps.get(&signal)
+                && writeln!(stdout, "{}", format_trap(signal, action)).is_err()
+            {
+                return 1;
+            }
+        }
+        return status;
+    }
+
+    for signal in signals {
+        if let Err(err) = set_disposition(signal, action) {
+            write_error(stderr, &format!("trap: {}: {}", signal_name(signal), err));
+            status = 1;
+            continue;
+        }
+        match action {
+            Some(action) => state.traps.insert(signal, action.to_string()),
+            None => state.traps.remove(&signal),
+        };
+    }
+    status
+}
+
+/// `trap` builtin, see [`trap`].
+pub struct TrapBuiltin;
+
+impl Builtin for TrapBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        trap(args, state, io.stdout, io.stderr)
+    }
+
+    fn flags(&self) -> &str {
+        "p"
+    }
+
+    /// Signal names, after the commands to run.
+    fn complete(
+        &self,
+        args: &[String],
+        word: &str,
+        _state: &mut ShellState,
+    ) -> Option<Vec<Candidate>> {
+        if args.is_empty() {
+            return None;
+        }
+        let word = word.to_ascii_uppercase();
+        let candidates = SIGNALS
+            .iter()
+            .filter(|(name, _)| name.starts_with(&word))
+            .map(|(name, _)| Candidate {
+                text: name.to_string(),
+                display: name.to_string(),
+            })
+            .collect();
+        Some(candidates)
+    }
+}
@@ -0,0 +1,54 @@
+use crate::{Builtin, ShellState, Streams, is_name, write_error};
+use std::io::Write;
+
+/// Remove shell variables or functions.
+///
+/// # Parameters
+/// - `args`: names of the variables, optionally preceded by `-v`, or of
+///   the functions after `-f`. Without option, a name that is not a
+///   variable removes the function of that name.
+/// - `state`: shell state holding the variables and functions.
+/// - `stderr`: stream errors are written to.
+///
+/// # Returns
+/// - `0` on success, `1` if a name is not a valid identifier.
+pub fn unset(args: &[String], state: &mut ShellState, stderr: &mut dyn Write) -> i32 {
+    let option = args.first().map(String::as_str);
+    let names = match option {
+        Some("-v" | "-f") => &args[1..],
+        _ => args,
+    };
+    let mut status = 0;
+    for name in names {
+        if option == Some("-f") {
+            state.functions.remove(name);
+            continue;
+        }
+        if option != Some("-v")
+            && !state.vars.contains_key(name)
+            && state.functions.remove(name).is_some()
+        {
+            continue;
+        }
+        if !is_name(name) {
+            write_error(stderr, &format!("unset: `{}': not a valid identifier", name));
+            status = 1;
+            continue;
+        }
+        state.unset_var(name);
+    }
+    status
+}
+
+/// `unset` builtin, see [`unset`].
+pub struct UnsetBuiltin;
+
+impl Builtin for UnsetBuiltin {
+    fn run(&self, args: &[String], state: &mut ShellState, io: &mut Streams) -> i32 {
+        unset(args, state, io.stderr)
+    }
+
+    fn flags(&self) -> &str {
+        "fv"
+    }
+}
@@ -0,0 +1,225 @@
+mod common;
+
+use common::*;
+use shell::*;
+use std::fs;
+use std::process;
+
+#[test]
+fn pwd_prints_the_plain_directory() {
+    let mut state = ShellState::new();
+    let (status, stdout, _) = run("pwd", &[], &mut state, "");
+    assert_eq!(status, 0);
+    assert_eq!(stdout, format!("{}\n", state.current_dir.display()));
+}
+
+#[test]
+fn ls_writes_no_colours_to_a_stream() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("sub")).unwrap();
+    fs::write(dir.path().join("file"), "").unwrap();
+    let mut state = ShellState::new();
+    state.current_dir = dir.path().to_path_buf();
+    let (status, stdout, _) = run("ls", &[], &mut state, "");
+    assert_eq!(status, 0);
+    assert!(!stdout.contains('\x1b'), "{:?}", stdout);
+    assert!(stdout.contains("file") && stdout.contains("sub"));
+}
+
+#[test]
+fn bad_loop_count_leaves_the_loops() {
+    let mut state = ShellState::new();
+    let script = "for i in 1 2; do while true; do break 0; done; echo no; done";
+    assert_eq!(run_script(script, "test", &mut state), 1);
+    assert_eq!(state.flow, None);
+    let script = "while true; do continue x; done";
+    assert_eq!(run_script(script, "test", &mut state), 1);
+}
+
+#[test]
+fn break_leaves_the_given_number_of_loops() {
+    let mut state = ShellState::new();
+    let script = "for i in 1 2; do for j in 1 2; do break 2; done; i=no; done";
+    assert_eq!(run_script(script, "test", &mut state), 0);
+    assert_eq!(state.get_var("i"), Some("1"));
+}
+
+#[test]
+fn echo_joins_its_arguments() {
+    let mut state = ShellState::new();
+    let (status, stdout, _) = run("echo", &["a", "b c"], &mut state, "");
+    assert_eq!((status, stdout.as_str()), (0, "a b c\n"));
+}
+
+#[test]
+fn cat_copies_stdin() {
+    let mut state = ShellState::new();
+    let (status, stdout, _) = run("cat", &[], &mut state, "line 1\nline 2\n");
+    assert_eq!((status, stdout.as_str()), (0, "line 1\nline 2\n"));
+}
+
+#[test]
+fn file_builtins_work_in_the_shell_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut state = ShellState::new();
+    state.current_dir = dir.path().to_path_buf();
+    fs::write(dir.path().join("f"), "text\n").unwrap();
+
+    assert_eq!(run("mkdir", &["d"], &mut state, "").0, 0);
+    assert_eq!(run("cp", &["f", "d"], &mut state, "").0, 0);
+    assert_eq!(run("mv", &["d/f", "d/g"], &mut state, "").0, 0);
+    let (status, stdout, _) = run("cat", &["d/g"], &mut state, "");
+    assert_eq!((status, stdout.as_str()), (0, "text\n"));
+    assert_eq!(run("rm", &["-r", "d"], &mut state, "").0, 0);
+    assert!(!dir.path().join("d").exists());
+
+    let (status, _, stderr) = run("cat", &["missing"], &mut state, "");
+    assert_eq!(status, 1);
+    assert!(stderr.contains("cat: missing"), "{:?}", stderr);
+}
+
+#[test]
+fn export_and_unset_variables() {
+    let mut state = ShellState::new();
+    assert_eq!(run("export", &["NEW_VAR=1"], &mut state, "").0, 0);
+    assert!(state.vars["NEW_VAR"].exported);
+    assert_eq!(run("unset", &["NEW_VAR"], &mut state, "").0, 0);
+    assert_eq!(state.get_var("NEW_VAR"), None);
+}
+
+#[test]
+fn functions_keep_locals_and_return_a_status() {
+    let mut state = ShellState::new();
+    let script = "x=outer; f() { local x=inner; y=$x; return 3; }; f";
+    assert_eq!(run_script(script, "test", &mut state), 3);
+    assert_eq!(state.get_var("x"), Some("outer"));
+    assert_eq!(state.get_var("y"), Some("inner"));
+    let (status, stdout, _) = run("declare", &["-F"], &mut state, "");
+    assert_eq!((status, stdout.as_str()), (0, "declare -f f\n"));
+    let (status, _, stderr) = run("local", &["z=1"], &mut state, "");
+    assert_eq!(status, 1);
+    assert!(stderr.contains("local"), "{:?}", stderr);
+}
+
+#[test]
+fn aliases_are_defined_printed_and_removed() {
+    let mut state = ShellState::new();
+    assert_eq!(run("alias", &["ll=ls -l"], &mut state, "").0, 0);
+    let (_, stdout, _) = run("alias", &[], &mut state, "");
+    assert_eq!(stdout, "alias ll='ls -l'\n");
+    let (_, stdout, _) = run("type", &["ll", "cd"], &mut state, "");
+    assert_eq!(stdout, "ll is aliased to `ls -l'\ncd is a shell builtin\n");
+    assert_eq!(run("unalias", &["ll"], &mut state, "").0, 0);
+    assert_eq!(run("unalias", &["ll"], &mut state, "").0, 1);
+}
+
+#[test]
+fn registered_closures_run_like_builtins() {
+    let mut state = ShellState::new();
+    state.builtins.register(
+        "hello",
+        |args: &[String], _state: &mut ShellState, io: &mut Streams| {
+            let _ = writeln!(io.stdout, "hello {}", args.join(" "));
+            0
+        },
+    );
+    let (status, stdout, _) = run("hello", &["you"], &mut state, "");
+    assert_eq!((status, stdout.as_str()), (0, "hello you\n"));
+}
+
+#[test]
+fn complete_runs_functions_for_candidates() {
+    let mut state = ShellState::new();
+    let script = "_svc() { echo start; echo stop; echo \"$1:$2:$3\"; }";
+    assert_eq!(run_script(script, "test", &mut state), 0);
+    assert_eq!(run("complete", &["-F", "_svc", "svc"], &mut state, "").0, 0);
+    let (_, stdout, _) = run("complete", &["-p", "svc"], &mut state, "");
+    assert_eq!(stdout, "complete -F _svc svc\n");
+
+    let spec = state.completions["svc"].clone();
+    let shown = |candidates: Vec<Candidate>| {
+        candidates
+            .into_iter()
+            .map(|candidate| candidate.display)
+            .collect::<Vec<_>>()
+    };
+    let candidates = spec.candidates("svc", "st", "svc", &mut state);
+    assert_eq!(shown(candidates), ["start", "stop"]);
+    let candidates = spec.candidates("svc", "svc:", "prev", &mut state);
+    assert_eq!(shown(candidates), ["svc:svc::prev"]);
+
+    let missing = CompletionSpec::Function("_none".to_string());
+    assert!(missing.candidates("svc", "", "svc", &mut state).is_empty());
+}
+
+#[test]
+fn env_options_change_the_environment_of_env_only() {
+    let mut state = ShellState::new();
+    state.set_var("KEPT", "1");
+    assert_eq!(run("export", &["KEPT"], &mut state, "").0, 0);
+    let (status, stdout, _) = run("env", &["-i", "A=b"], &mut state, "");
+    assert_eq!((status, stdout.as_str()), (0, "A=b\n"));
+    let (_, stdout, _) = run("env", &["-u", "KEPT"], &mut state, "");
+    assert!(!stdout.contains("KEPT="));
+    assert!(state.vars["KEPT"].exported);
+
+    let (status, stdout, stderr) = run("env", &["-x", "cmd"], &mut state, "");
+    assert_eq!((status, stdout.as_str()), (125, ""));
+    assert!(stderr.contains("env: invalid option -- 'x'"));
+    assert_eq!(run("env", &["-u"], &mut state, "").0, 125);
+}
+
+#[test]
+fn cat_copies_files_larger_than_a_block() {
+    let dir = tempfile::tempdir().unwrap();
+    let content = "0123456789abcdef".repeat(10_000);
+    fs::write(dir.path().join("big"), &content).unwrap();
+    fs::write(dir.path().join("small"), "end\n").unwrap();
+    let mut state = ShellState::new();
+    state.current_dir = dir.path().to_path_buf();
+    let (status, stdout, _) = run("cat", &["big", "small"], &mut state, "");
+    assert_eq!(status, 0);
+    assert_eq!(stdout, content + "end\n");
+
+    let (status, _, stderr) = run("cat", &["."], &mut state, "");
+    assert_eq!(status, 1);
+    assert!(stderr.contains("cat: ."), "{:?}", stderr);
+}
+
+#[test]
+fn traps_are_printed_as_commands() {
+    let mut state = ShellState::new();
+    assert_eq!(run("trap", &["echo bye", "EXIT"], &mut state, "").0, 0);
+    let (_, stdout, _) = run("trap", &["-p"], &mut state, "");
+    assert_eq!(stdout, "trap -- 'echo bye' EXIT\n");
+    let (status, _, stderr) = run("trap", &["x", "NOPE"], &mut state, "");
+    assert_eq!(status, 1);
+    assert!(stderr.contains("invalid signal specification"));
+}
+
+#[test]
+fn wait_skips_stopped_jobs_and_keeps_them() {
+    let mut state = ShellState::new();
+    let mut sleep = process::Command::new("sleep").arg("10").spawn().unwrap();
+    let mut done = process::Command::new("true").spawn().unwrap();
+    let (stopped, running) = (sleep.id() as i32, done.id() as i32);
+    unsafe {
+        // Wait for the stop without reaping it, so that `wait` sees it too
+        libc::kill(stopped, libc::SIGSTOP);
+        let mut info = std::mem::zeroed();
+        let flags = libc::WSTOPPED | libc::WNOWAIT;
+        libc::waitid(libc::P_PID, stopped as libc::id_t, &mut info, flags);
+    }
+    let job = Job::new(stopped, vec![stopped], "sleep 10".into());
+    add_job(job, &mut state);
+    add_job(Job::new(running, vec![running], "true".into()), &mut state);
+
+    assert_eq!(run("wait", &[], &mut state, "").0, 0);
+    let left = state.jobs.iter().map(|job| job.pgid).collect::<Vec<_>>();
+    assert_eq!(left, [stopped]);
+    assert_eq!(state.jobs[0].state, JobState::Stopped);
+    // Reaped by `wait`
+    assert!(done.wait().is_err());
+    sleep.kill().unwrap();
+    sleep.wait().unwrap();
+}
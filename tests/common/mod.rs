@@ -0,0 +1,39 @@
+//! Fixtures shared by the integration tests. Each test crate uses only
+//! some of them.
+#![allow(dead_code)]
+
+use shell::*;
+
+/// A shell state with `$0` and the positional parameters set.
+pub fn state_with_args(args: &[&str]) -> ShellState {
+    let mut state = ShellState::new();
+    state.arg0 = "0-shell".to_string();
+    state.positional = args.iter().map(|arg| arg.to_string()).collect();
+    state
+}
+
+/// Run the builtin `name` in-process on `input`, capturing what it writes.
+///
+/// # Returns
+/// - its exit status, stdout and stderr.
+pub fn run(
+    name: &str,
+    args: &[&str],
+    state: &mut ShellState,
+    input: &str,
+) -> (i32, String, String) {
+    let builtin = state.builtins.get(name).expect("no such builtin");
+    let args = args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+    let mut stdin = input.as_bytes();
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+    let mut io = Streams {
+        stdin: &mut stdin,
+        stdout: &mut stdout,
+        stderr: &mut stderr,
+        terminal: false,
+    };
+    let status = builtin.run(&args, state, &mut io);
+    let stdout = String::from_utf8(stdout).unwrap();
+    (status, stdout, String::from_utf8(stderr).unwrap())
+}
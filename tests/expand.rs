@@ -0,0 +1,78 @@
+mod common;
+
+use common::*;
+use shell::*;
+
+#[test]
+fn positional_parameters() {
+    let mut state = state_with_args(&["a", "b"]);
+    assert_eq!(expand_string("$1-${2}-${3}", &mut state).unwrap(), "a-b-");
+    assert_eq!(expand_string("$#", &mut state).unwrap(), "2");
+}
+
+#[test]
+fn zero_digit_names_are_arg0() {
+    let mut state = state_with_args(&["a"]);
+    assert_eq!(expand_string("${0}", &mut state).unwrap(), "0-shell");
+    assert_eq!(expand_string("${00}", &mut state).unwrap(), "0-shell");
+    assert_eq!(expand_string("${000}", &mut state).unwrap(), "0-shell");
+    assert_eq!(expand_string("${01}", &mut state).unwrap(), "a");
+}
+
+#[test]
+fn command_substitution_of_pwd_is_the_directory() {
+    let mut state = ShellState::new();
+    let dir = expand_string("$(pwd)", &mut state).unwrap();
+    assert_eq!(dir, state.current_dir.display().to_string());
+}
+
+#[test]
+fn variables_and_defaults() {
+    let mut state = ShellState::new();
+    state.set_var("NAME", "world");
+    state.set_var("EMPTY", "");
+    assert_eq!(
+        expand_string("hello $NAME", &mut state).unwrap(),
+        "hello world"
+    );
+    assert_eq!(expand_string("${#NAME}", &mut state).unwrap(), "5");
+    assert_eq!(
+        expand_string("${UNSET-x}${EMPTY-y}", &mut state).unwrap(),
+        "x"
+    );
+    assert_eq!(expand_string("${EMPTY:-z}", &mut state).unwrap(), "z");
+    assert_eq!(expand_string("${NAME:+set}", &mut state).unwrap(), "set");
+    assert_eq!(expand_string("${NEW:=v}$NEW", &mut state).unwrap(), "vv");
+    assert!(expand_string("${NAME%}", &mut state).is_err());
+}
+
+#[test]
+fn quotes_and_field_splitting() {
+    let mut state = ShellState::new();
+    state.set_var("WORDS", "a  b");
+    assert_eq!(expand_word("$WORDS", &mut state).unwrap(), ["a", "b"]);
+    assert_eq!(expand_word("\"$WORDS\"", &mut state).unwrap(), ["a  b"]);
+    assert_eq!(expand_word("'$WORDS'", &mut state).unwrap(), ["$WORDS"]);
+    assert_eq!(expand_word("a\\ b", &mut state).unwrap(), ["a b"]);
+    assert!(expand_word("$UNSET", &mut state).unwrap().is_empty());
+    assert_eq!(expand_word("\"\"", &mut state).unwrap(), [""]);
+}
+
+#[test]
+fn tilde_is_the_home_directory() {
+    let mut state = ShellState::new();
+    state.set_var("HOME", "/home/user");
+    assert_eq!(expand_string("~/x", &mut state).unwrap(), "/home/user/x");
+    assert_eq!(expand_string("'~'", &mut state).unwrap(), "~");
+}
+
+#[test]
+fn special_parameters() {
+    let mut state = ShellState::new();
+    state.last_command_staus = 3;
+    assert_eq!(expand_string("$?", &mut state).unwrap(), "3");
+    assert_eq!(
+        expand_string("$$", &mut state).unwrap(),
+        state.pid.to_string()
+    );
+}
@@ -0,0 +1,37 @@
+use shell::*;
+use std::fs;
+
+#[test]
+fn patterns_match_whole_texts() {
+    assert!(pattern_matches("*.rs", "main.rs"));
+    assert!(!pattern_matches("*.rs", "main.rs.bak"));
+    assert!(pattern_matches("?at", "cat"));
+    assert!(!pattern_matches("?at", "at"));
+    assert!(pattern_matches("[a-c]x", "bx"));
+    assert!(!pattern_matches("[!a-c]x", "bx"));
+    assert!(pattern_matches("\\*", "*"));
+    assert!(!pattern_matches("\\*", "a"));
+}
+
+#[test]
+fn only_unescaped_wildcards_are_globs() {
+    assert!(has_glob("*.txt"));
+    assert!(has_glob("file[12]"));
+    assert!(!has_glob("\\*.txt"));
+    assert!(!has_glob("plain"));
+}
+
+#[test]
+fn glob_lists_matching_paths_sorted() {
+    let dir = tempfile::tempdir().unwrap();
+    for name in ["b.txt", "a.txt", ".hidden.txt", "c.md"] {
+        fs::write(dir.path().join(name), "").unwrap();
+    }
+    fs::create_dir_all(dir.path().join("sub/deep")).unwrap();
+    fs::write(dir.path().join("sub/deep/d.txt"), "").unwrap();
+
+    assert_eq!(glob("*.txt", dir.path(), false), ["a.txt", "b.txt"]);
+    assert_eq!(glob("*/*/*.txt", dir.path(), false), ["sub/deep/d.txt"]);
+    assert_eq!(glob("**/d.txt", dir.path(), true), ["sub/deep/d.txt"]);
+    assert!(glob("*.rs", dir.path(), false).is_empty());
+}
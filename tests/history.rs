@@ -0,0 +1,92 @@
+mod common;
+
+use common::*;
+use shell::*;
+use std::fs;
+
+#[test]
+fn read_keeps_unsaved_entries() {
+    let dir = tempfile::tempdir().unwrap();
+    let old = dir.path().join("old");
+    let new = dir.path().join("new");
+    fs::write(&old, "from file\n").unwrap();
+    let mut state = ShellState::new();
+    state.current_dir = dir.path().to_path_buf();
+    state.hist.push("typed", "", None);
+
+    assert_eq!(run("history", &["-r", "old"], &mut state, "").0, 0);
+    assert_eq!(state.hist.entries(), ["from file", "typed"]);
+    assert_eq!(state.hist.unsaved(), ["typed"]);
+
+    assert_eq!(run("history", &["-a", "new"], &mut state, "").0, 0);
+    assert_eq!(fs::read_to_string(&new).unwrap(), "typed\n");
+    assert!(state.hist.unsaved().is_empty());
+}
+
+#[test]
+fn push_follows_histcontrol() {
+    let mut hist = History::default();
+    assert!(hist.push("ls", "ignoreboth", None));
+    assert!(!hist.push("ls", "ignoreboth", None));
+    assert!(!hist.push(" secret", "ignoreboth", None));
+    assert!(hist.push("cd", "", None));
+    assert!(hist.push("ls", "erasedups", None));
+    assert_eq!(hist.entries(), ["cd", "ls"]);
+    assert_eq!(hist.unsaved(), ["cd", "ls"]);
+}
+
+#[test]
+fn size_limits_drop_the_oldest_entries() {
+    let mut hist = History::default();
+    for entry in ["a", "b", "c"] {
+        hist.push(entry, "", Some(2));
+    }
+    assert_eq!(hist.entries(), ["b", "c"]);
+    hist.mark_saved();
+    hist.push("d", "", None);
+    hist.truncate(1);
+    assert_eq!(hist.entries(), ["d"]);
+    assert_eq!(hist.unsaved(), ["d"]);
+}
+
+#[test]
+fn remove_and_clear() {
+    let mut hist = History::default();
+    hist.extend(vec!["a".to_string(), "b".to_string()]);
+    hist.push("c", "", None);
+    assert_eq!(hist.remove(2).as_deref(), Some("c"));
+    assert!(hist.unsaved().is_empty());
+    assert_eq!(hist.remove(5), None);
+    hist.clear();
+    assert!(hist.is_empty());
+}
+
+#[test]
+fn write_and_read_back_multiline_entries() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut state = ShellState::new();
+    state.current_dir = dir.path().to_path_buf();
+    state.hist.push("for i in 1\ndo echo \\$i; done", "", None);
+    assert_eq!(run("history", &["-w", "file"], &mut state, "").0, 0);
+    assert_eq!(run("history", &["-c"], &mut state, "").0, 0);
+    assert_eq!(run("history", &["-r", "file"], &mut state, "").0, 0);
+    assert_eq!(state.hist.entries(), ["for i in 1\ndo echo \\$i; done"]);
+}
+
+#[test]
+fn bang_is_literal_in_dollar_bang_and_before_operators() {
+    let hist = ["sleep 1".to_string(), "echo last".to_string()];
+    for line in [
+        "sleep 0 & echo $!; echo done",
+        "echo \"$!\"",
+        "echo hi!; a!|b!&c!>d!<e!)",
+        "echo !'x' !\"y\"",
+        "echo ! != x!",
+    ] {
+        assert_eq!(expand_history(line, &hist), Ok(None), "{:?}", line);
+    }
+    assert_eq!(
+        expand_history("!! !s !:1 !$", &hist),
+        Ok(Some("echo last sleep 1 last last".to_string()))
+    );
+}
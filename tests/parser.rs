@@ -0,0 +1,142 @@
+use shell::*;
+
+fn parse(input: &str) -> Result<List, ParseError> {
+    input.to_string().custom_split()
+}
+
+/// The simple command of a pipeline made of a single one.
+fn simple(pipeline: &Pipeline) -> &Command {
+    match pipeline.commands.as_slice() {
+        [Stage::Simple(command)] => command,
+        stages => panic!("not a simple command: {:?}", stages),
+    }
+}
+
+#[test]
+fn simple_command_with_assignment_and_redirects() {
+    let list = parse("A=1 echo 'a b' c 2>> err.log").unwrap();
+    assert_eq!(list.items.len(), 1);
+    let command = simple(&list.items[0].first);
+    assert_eq!(command.assignments, [("A".to_string(), "1".to_string())]);
+    assert_eq!(command.name, "echo");
+    assert_eq!(command.args, ["'a b'", "c"]);
+    let redirect = Redirect {
+        fd: 2,
+        kind: RedirectKind::Append,
+        target: "err.log".to_string(),
+    };
+    assert_eq!(command.redirects, [redirect]);
+}
+
+#[test]
+fn lists_and_or_lists_and_pipelines() {
+    let list = parse("a | b && ! c || d; e &\nf").unwrap();
+    assert_eq!(list.items.len(), 3);
+    let and_or = &list.items[0];
+    assert_eq!(and_or.first.commands.len(), 2);
+    let connectors = and_or.rest.iter().map(|(c, _)| *c).collect::<Vec<_>>();
+    assert_eq!(connectors, [Connector::And, Connector::Or]);
+    assert!(and_or.rest[0].1.negated);
+    assert!(list.items[1].background);
+    assert!(!list.items[2].background);
+}
+
+#[test]
+fn empty_input_and_comments() {
+    assert!(parse("").unwrap().is_empty());
+    assert!(parse("  # only a comment").unwrap().is_empty());
+}
+
+#[test]
+fn incomplete_input_asks_for_more() {
+    for input in ["echo 'a", "a |", "a &&", "echo \\"] {
+        assert!(
+            matches!(parse(input), Err(ParseError::Incomplete(_))),
+            "{:?}",
+            input
+        );
+    }
+}
+
+#[test]
+fn misplaced_operators_are_syntax_errors() {
+    for input in ["| a", "a ;; b", "a & ;", "a || && b"] {
+        assert!(
+            matches!(parse(input), Err(ParseError::Syntax(_))),
+            "{:?}",
+            input
+        );
+    }
+}
+
+#[test]
+fn compound_commands() {
+    let list = parse("while a; do b; done > out").unwrap();
+    match &list.items[0].first.commands[0] {
+        Stage::Compound(Compound::While { until, body, .. }, redirects) => {
+            assert!(!until);
+            assert_eq!(body.items.len(), 1);
+            assert_eq!(redirects[0].target, "out");
+        }
+        stage => panic!("not a while loop: {:?}", stage),
+    }
+    let list = parse("case $x in a|b) c;; *) d;; esac").unwrap();
+    match &list.items[0].first.commands[0] {
+        Stage::Compound(Compound::Case { word, items }, _) => {
+            assert_eq!(word, "$x");
+            assert_eq!(items[0].patterns, ["a", "b"]);
+            assert_eq!(items[1].patterns, ["*"]);
+        }
+        stage => panic!("not a case command: {:?}", stage),
+    }
+}
+
+#[test]
+fn unfinished_and_misplaced_compound_commands() {
+    for input in ["if true; then", "while a; do", "case x in a)", "{ a;"] {
+        assert!(
+            matches!(parse(input), Err(ParseError::Incomplete(_))),
+            "{:?}",
+            input
+        );
+    }
+    for input in ["fi", "for 1x in a; do b; done", "if a; fi", "done"] {
+        assert!(
+            matches!(parse(input), Err(ParseError::Syntax(_))),
+            "{:?}",
+            input
+        );
+    }
+}
+
+#[test]
+fn command_substitution_is_one_word() {
+    let list = parse("echo $(case a in a) echo x;; esac) `b c` \"$(d)\"").unwrap();
+    let command = simple(&list.items[0].first);
+    assert_eq!(
+        command.args,
+        ["$(case a in a) echo x;; esac)", "`b c`", "\"$(d)\""]
+    );
+    for input in ["echo $(ls", "echo $(case a in a)", "echo `ls"] {
+        assert!(
+            matches!(parse(input), Err(ParseError::Incomplete(_))),
+            "{:?}",
+            input
+        );
+    }
+}
+
+#[test]
+fn displayed_code_parses_back_the_same() {
+    for input in [
+        "a | b && c || d &",
+        "if a; then b; elif c; then d; else e; fi",
+        "for i in 1 2; do echo $i; done 2>&1",
+        "f() { local x=1; return 2; }",
+        "case $x in a | b) c;; esac",
+    ] {
+        let list = parse(input).unwrap();
+        let shown = list.items[0].to_string();
+        assert_eq!(parse(&shown).unwrap(), list, "{:?}", shown);
+    }
+}